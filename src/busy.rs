@@ -0,0 +1,99 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use tokio::{sync::Notify, time::timeout};
+
+/// Number of workers of a crawl which are busy
+///
+/// The crawl is done once no worker is busy and the queue is empty, as only
+/// busy workers queue more urls.
+#[derive(Debug, Default)]
+pub struct BusyWorkers {
+    busy: AtomicUsize,
+    done: Notify,
+}
+
+impl BusyWorkers {
+    /// All `workers` start out busy
+    pub fn new(workers: usize) -> Self {
+        Self {
+            busy: AtomicUsize::new(workers),
+            done: Notify::new(),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.busy.load(Ordering::SeqCst)
+    }
+
+    /// Marks a worker as idle
+    pub fn idle(&self) {
+        if self.busy.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.done.notify_waiters();
+        }
+    }
+
+    /// Marks an idle worker as busy again
+    pub fn busy(&self) {
+        self.busy.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Waits until no worker is busy, at most for `duration`
+    pub async fn wait_timeout(&self, duration: Duration) {
+        // notified from here on
+        let done = self.done.notified();
+        if self.count() == 0 {
+            return;
+        }
+        let _ = timeout(duration, done).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Instant};
+
+    use tokio::runtime::Builder as RuntimeBuilder;
+
+    use super::*;
+
+    #[test]
+    fn wakes_once_done() {
+        let runtime = RuntimeBuilder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let busy = Arc::new(BusyWorkers::new(2));
+
+        let waiting = busy.clone();
+        let start = Instant::now();
+        let waited = runtime.spawn(async move {
+            waiting.wait_timeout(Duration::from_secs(10)).await;
+            start.elapsed()
+        });
+
+        std::thread::sleep(Duration::from_millis(50));
+        busy.idle();
+        busy.idle();
+
+        assert!(runtime.block_on(waited).unwrap() < Duration::from_secs(10));
+        assert_eq!(0, busy.count());
+    }
+
+    #[test]
+    fn done() {
+        let runtime = RuntimeBuilder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let busy = BusyWorkers::new(1);
+        busy.idle();
+
+        let start = Instant::now();
+        runtime.block_on(busy.wait_timeout(Duration::from_secs(10)));
+
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,140 @@
+use std::ops::Range;
+
+use encoding_rs::{Encoding, UTF_8};
+
+/// How many bytes to scan for a `<meta charset>` declaration, browsers use
+/// the same limit
+const META_PRESCAN_LENGTH: usize = 1024;
+
+/// Determines the encoding of a document.
+///
+/// The `charset` parameter of the `Content-Type` header takes precedence over
+/// a `<meta>` declaration. Falls back to UTF-8. A byte order mark still
+/// overrides the result when decoding.
+pub fn detect(content_type: Option<&str>, document: &[u8]) -> &'static Encoding {
+    content_type
+        .and_then(content_type_charset)
+        .or_else(|| meta_charset(document).map(|range| &document[range]))
+        .and_then(Encoding::for_label)
+        .unwrap_or(UTF_8)
+}
+
+/// Rewrites the `<meta>` charset declaration of an already decoded document to
+/// UTF-8 so the re-encoded file is read correctly.
+pub fn declare_utf8(document: &str) -> String {
+    match meta_charset(document.as_bytes()) {
+        Some(range) => {
+            let mut document = document.to_string();
+            document.replace_range(range, "utf-8");
+            document
+        }
+        None => document.to_string(),
+    }
+}
+
+fn content_type_charset(content_type: &str) -> Option<&[u8]> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| value.trim().trim_matches('"').as_bytes())
+}
+
+/// Finds the charset label in `<meta charset="...">` or
+/// `<meta http-equiv="Content-Type" content="text/html; charset=...">`
+fn meta_charset(document: &[u8]) -> Option<Range<usize>> {
+    let prefix = &document[..document.len().min(META_PRESCAN_LENGTH)];
+    let lowercase = prefix.to_ascii_lowercase();
+
+    let mut offset = 0;
+    while let Some(start) = find(&lowercase[offset..], b"<meta") {
+        let tag_start = offset + start;
+        let tag_end =
+            find(&lowercase[tag_start..], b">").map_or(lowercase.len(), |end| tag_start + end);
+        let tag = &lowercase[tag_start..tag_end];
+
+        if let Some(position) = find(tag, b"charset=") {
+            let value_start = tag_start + position + "charset=".len();
+            let value_start = value_start
+                + lowercase[value_start..tag_end]
+                    .iter()
+                    .take_while(|&&b| b == b'"' || b == b'\'')
+                    .count();
+            let value_length = lowercase[value_start..tag_end]
+                .iter()
+                .take_while(|&&b| {
+                    !matches!(b, b'"' | b'\'' | b';' | b'/' | b'>') && !b.is_ascii_whitespace()
+                })
+                .count();
+
+            if value_length > 0 {
+                return Some(value_start..value_start + value_length);
+            }
+        }
+
+        offset = tag_end;
+    }
+
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod test {
+    use encoding_rs::{SHIFT_JIS, UTF_8, WINDOWS_1252};
+
+    use super::*;
+
+    const LATIN1_DOCUMENT: &[u8] =
+        b"<html><head><meta charset=\"iso-8859-1\"></head><body>Gr\xfc\xdfe</body></html>";
+
+    #[test]
+    fn meta_charset_latin1() {
+        let encoding = detect(Some("text/html"), LATIN1_DOCUMENT);
+        assert_eq!(WINDOWS_1252, encoding);
+
+        let (document, _, had_errors) = encoding.decode(LATIN1_DOCUMENT);
+        assert!(!had_errors);
+        assert_eq!(
+            "<html><head><meta charset=\"iso-8859-1\"></head><body>Grüße</body></html>",
+            document
+        );
+    }
+
+    #[test]
+    fn resave_as_utf8() {
+        let (document, _, _) = detect(None, LATIN1_DOCUMENT).decode(LATIN1_DOCUMENT);
+        let document = declare_utf8(&document);
+
+        assert_eq!(
+            "<html><head><meta charset=\"utf-8\"></head><body>Grüße</body></html>".as_bytes(),
+            document.as_bytes()
+        );
+    }
+
+    #[test]
+    fn content_type_takes_precedence() {
+        assert_eq!(
+            SHIFT_JIS,
+            detect(Some("text/html; charset=Shift_JIS"), LATIN1_DOCUMENT)
+        );
+    }
+
+    #[test]
+    fn http_equiv() {
+        let document =
+            b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1252\">";
+        assert_eq!(WINDOWS_1252, detect(None, document));
+    }
+
+    #[test]
+    fn defaults_to_utf8() {
+        assert_eq!(UTF_8, detect(None, b"<html><body>Hello</body></html>"));
+    }
+}
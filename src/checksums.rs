@@ -0,0 +1,136 @@
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use dashmap::DashMap;
+use itertools::Itertools;
+use sha2::{Digest, Sha256};
+
+use crate::{rotate::Rotation, Error, Result};
+
+/// Name of the manifest in the output directory
+pub const MANIFEST_FILE_NAME: &str = "SHA256SUMS";
+
+/// SHA-256 digests of saved files by their path relative to the output directory
+#[derive(Debug, Default)]
+pub struct Checksums {
+    sums: DashMap<PathBuf, String>,
+}
+
+impl Checksums {
+    pub fn insert(&self, path: PathBuf, digest: String) {
+        self.sums.insert(path, digest);
+    }
+
+    /// Records the digest of `contents` written to `path` after saving
+    pub fn insert_contents(&self, path: PathBuf, contents: &[u8]) {
+        self.insert(path, format!("{:x}", Sha256::digest(contents)));
+    }
+
+    pub fn remove(&self, path: &Path) {
+        self.sums.remove(path);
+    }
+
+    /// Keeps the digest of the file moved from `from` to `to`
+    pub fn rename(&self, from: &Path, to: &Path) {
+        if let Some((_, digest)) = self.sums.remove(from) {
+            self.insert(to.to_path_buf(), digest);
+        }
+    }
+
+    /// Writes a `sha256sum` compatible manifest to `output_path`
+    pub fn write_manifest(&self, output_path: &Path, rotation: Rotation) -> Result<()> {
+        let mut writer = rotation
+            .create(&output_path.join(MANIFEST_FILE_NAME))
+            .map_err(Error::CreateFile)?;
+
+        self.sums
+            .iter()
+            .sorted_by(|a, b| a.key().cmp(b.key()))
+            .try_for_each(|entry| writeln!(writer, "{}  {}", entry.value(), entry.key().display()))
+            .and_then(|_| writer.finish())
+            .map_err(Error::WriteFile)
+    }
+}
+
+/// Computes the SHA-256 digest of everything written through it
+#[derive(Debug)]
+pub struct HashingWriter<W> {
+    inner: W,
+    hasher: Option<Sha256>,
+}
+
+impl<W> HashingWriter<W> {
+    /// Hashing is skipped entirely unless `enabled`
+    pub fn new(inner: W, enabled: bool) -> Self {
+        Self {
+            inner,
+            hasher: enabled.then(Sha256::new),
+        }
+    }
+
+    /// Hashes `bytes` written before, like the start of a resumed download
+    pub fn hash_written(&mut self, bytes: &[u8]) {
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(bytes);
+        }
+    }
+
+    /// Hex encoded digest of the written bytes
+    pub fn digest(self) -> Option<String> {
+        self.hasher.map(|hasher| format!("{:x}", hasher.finalize()))
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::read_to_string;
+
+    use super::*;
+
+    #[test]
+    fn manifest() {
+        let output = tempfile::tempdir().unwrap();
+
+        let mut writer = HashingWriter::new(Vec::new(), true);
+        writer.write_all(b"hello ").unwrap();
+        writer.write_all(b"world").unwrap();
+
+        let checksums = Checksums::default();
+        checksums.insert(
+            PathBuf::from("example.com/index.html"),
+            writer.digest().unwrap(),
+        );
+        checksums
+            .write_manifest(output.path(), Rotation::default())
+            .unwrap();
+
+        assert_eq!(
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9  example.com/index.html\n",
+            read_to_string(output.path().join(MANIFEST_FILE_NAME)).unwrap()
+        );
+    }
+
+    #[test]
+    fn disabled() {
+        let mut writer = HashingWriter::new(Vec::new(), false);
+        writer.write_all(b"hello world").unwrap();
+
+        assert_eq!(None, writer.digest());
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+/// Future returned by [`Clock::sleep`]
+pub type Sleep<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+/// Source of time for everything waiting during a crawl
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// Completes once `duration` has passed on this clock
+    fn sleep(&self, duration: Duration) -> Sleep<'_>;
+}
+
+/// The wall clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep<'_> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// A clock which only moves when advanced, for testing
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<Instant>,
+    sleepers: Mutex<Vec<(Instant, Waker)>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            now: Mutex::new(Instant::now()),
+            sleepers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Moves the clock forward, waking all sleeps which are due
+    pub fn advance(&self, duration: Duration) {
+        let now = {
+            let mut now = self.now.lock().unwrap();
+            *now += duration;
+            *now
+        };
+
+        let mut sleepers = self.sleepers.lock().unwrap();
+        let (due, pending) = sleepers
+            .drain(..)
+            .partition::<Vec<_>, _>(|(deadline, _)| *deadline <= now);
+        *sleepers = pending;
+        drop(sleepers);
+
+        due.into_iter().for_each(|(_, waker)| waker.wake());
+    }
+
+    /// Number of sleeps waiting for the clock to advance
+    pub fn sleepers(&self) -> usize {
+        self.sleepers.lock().unwrap().len()
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep<'_> {
+        Box::pin(ManualSleep {
+            clock: self,
+            deadline: self.now() + duration,
+        })
+    }
+}
+
+struct ManualSleep<'a> {
+    clock: &'a ManualClock,
+    deadline: Instant,
+}
+
+impl Future for ManualSleep<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // registered under the lock so an advance can not slip in between
+        let mut sleepers = self.clock.sleepers.lock().unwrap();
+        if self.clock.now() >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        sleepers.retain(|(_, waker)| !waker.will_wake(cx.waker()));
+        sleepers.push((self.deadline, cx.waker().clone()));
+        Poll::Pending
+    }
+}
@@ -0,0 +1,128 @@
+//! TOML config files holding command line options
+//!
+//! The options are kept as the values they have on the command line instead
+//! of being deserialized into [`Settings`](crate::Settings). Many settings are
+//! parsed from a different form than they are stored in, like byte sizes with
+//! a suffix, delays in milliseconds or regexes, and several flags turn on more
+//! than one setting. Passing the values through the parsers of the command
+//! line keeps both forms and their validation the same, and every new flag
+//! works in config files without further changes.
+
+use std::{collections::BTreeMap, fs::read_to_string, path::Path};
+
+use serde::{Deserialize, Serialize};
+use toml::Value;
+
+use crate::{Error, Result};
+
+/// Options of a config file, named like the long flags on the command line
+/// which override them
+///
+/// Target URLs are given as `targets`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Config(BTreeMap<String, Value>);
+
+/// Value of an option in a config file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionValue {
+    /// Turns a flag on or off
+    Switch(bool),
+    /// Values of an option, one for each time it is given
+    Values(Vec<String>),
+}
+
+impl Config {
+    /// Reads the TOML config file at `path`
+    pub fn load(path: &Path) -> Result<Self> {
+        let config = read_to_string(path).map_err(Error::ReadFile)?;
+        toml::from_str(&config).map_err(Error::ParseConfig)
+    }
+
+    /// Names of the options with their values
+    pub fn options(&self) -> Result<Vec<(&str, OptionValue)>> {
+        self.0
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    Value::Boolean(on) => OptionValue::Switch(*on),
+                    Value::Array(values) => OptionValue::Values(
+                        values
+                            .iter()
+                            .map(scalar)
+                            .collect::<Option<_>>()
+                            .ok_or_else(|| Error::ConfigValue {
+                                option: name.clone(),
+                            })?,
+                    ),
+                    value => OptionValue::Values(vec![scalar(value).ok_or_else(|| {
+                        Error::ConfigValue {
+                            option: name.clone(),
+                        }
+                    })?]),
+                };
+
+                Ok((name.as_str(), value))
+            })
+            .collect()
+    }
+}
+
+/// `value` as it is written on the command line, if it is not a collection
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Integer(value) => Some(value.to_string()),
+        Value::Float(value) => Some(value.to_string()),
+        Value::Boolean(value) => Some(value.to_string()),
+        Value::Datetime(value) => Some(value.to_string()),
+        Value::Array(_) | Value::Table(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+targets = ["https://example.com/docs/"]
+output = "mirror"
+threads = 4
+delay = 250
+exclude = ["\\.pdf$"]
+user-agent = "docs-mirror/1.0"
+convert-links = true
+modified-since = 2022-03-01T00:00:00Z
+"#;
+
+    #[test]
+    fn sample() {
+        let config: Config = toml::from_str(SAMPLE).unwrap();
+
+        let values = |values: &[&str]| {
+            OptionValue::Values(values.iter().map(|value| value.to_string()).collect())
+        };
+        assert_eq!(
+            vec![
+                ("convert-links", OptionValue::Switch(true)),
+                ("delay", values(&["250"])),
+                ("exclude", values(&[r"\.pdf$"])),
+                ("modified-since", values(&["2022-03-01T00:00:00Z"])),
+                ("output", values(&["mirror"])),
+                ("targets", values(&["https://example.com/docs/"])),
+                ("threads", values(&["4"])),
+                ("user-agent", values(&["docs-mirror/1.0"])),
+            ],
+            config.options().unwrap()
+        );
+    }
+
+    #[test]
+    fn nested_values() {
+        let config: Config = toml::from_str("[login]\nuser = \"me\"").unwrap();
+        assert!(config.options().is_err());
+
+        let config: Config = toml::from_str("exclude = [[\"a\"]]").unwrap();
+        assert!(config.options().is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    ops::Range,
+    path::{Component, Path},
+};
+
+use tl::VDom;
+
+/// Tags and attributes holding links which are converted
+const LINK_ATTRIBUTES: [(&str, &str); 5] = [
+    ("a[href]", "href"),
+    ("link[href]", "href"),
+    ("img[src]", "src"),
+    ("script[src]", "src"),
+    ("source[src]", "src"),
+];
+
+/// Byte ranges of the link attribute values in `document`, which `dom` was
+/// parsed from
+pub fn link_spans(dom: &VDom, document: &str) -> Vec<Range<usize>> {
+    let start = document.as_ptr() as usize;
+
+    let mut spans = LINK_ATTRIBUTES
+        .iter()
+        .flat_map(|(selector, attribute)| {
+            dom.query_selector(selector)
+                .into_iter()
+                .flatten()
+                .filter_map(|handle| handle.get(dom.parser()))
+                .filter_map(|node| node.as_tag())
+                .filter_map(|tag| tag.attributes().get(*attribute).flatten())
+                .map(|bytes| bytes.as_bytes())
+                // values are slices of the document unless modified
+                .filter_map(|value| {
+                    let offset = (value.as_ptr() as usize).checked_sub(start)?;
+                    let end = offset.checked_add(value.len())?;
+                    (end <= document.len()).then(|| offset..end)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    spans.sort_by_key(|span| span.start);
+    spans.dedup();
+    spans
+}
+
+/// Replaces the non-overlapping `spans` of `document` in order
+pub fn replace(document: &str, replacements: Vec<(Range<usize>, String)>) -> String {
+    let mut replaced = String::with_capacity(document.len());
+    let mut end = 0;
+
+    for (span, replacement) in replacements {
+        replaced.push_str(&document[end..span.start]);
+        replaced.push_str(&replacement);
+        end = span.end;
+    }
+    replaced.push_str(&document[end..]);
+
+    replaced
+}
+
+/// Link from the file at `from` to the file at `to`, both relative to the
+/// output directory
+pub fn relative_link(from: &Path, to: &Path) -> String {
+    let from = from.parent().unwrap_or_else(|| Path::new(""));
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let parents = from.components().skip(common).map(|_| String::from(".."));
+    let rest = to
+        .components()
+        .skip(common)
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(escape(&name.to_string_lossy())),
+            _ => None,
+        });
+
+    parents.chain(rest).collect::<Vec<_>>().join("/")
+}
+
+/// Percent-encodes the characters of a file name which have a meaning in urls
+pub fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        match c {
+            '%' | '?' | '#' | ' ' | '"' | '\'' | '<' | '>' => {
+                escaped.push_str(&format!("%{:02X}", c as u8))
+            }
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn relative_links() {
+        assert_eq!(
+            "about.html",
+            relative_link(
+                Path::new("example.com/index.html"),
+                Path::new("example.com/about.html")
+            )
+        );
+        assert_eq!(
+            "../../css/style.css",
+            relative_link(
+                Path::new("example.com/docs/guide/index.html"),
+                Path::new("example.com/css/style.css")
+            )
+        );
+        assert_eq!(
+            "index.html%3Fpage=2",
+            relative_link(
+                Path::new("example.com/index.html"),
+                Path::new("example.com/index.html?page=2")
+            )
+        );
+    }
+
+    #[test]
+    fn spans() {
+        let document = r#"<a href="/a">a</a><img src='b.png'><p title="c">"#;
+        let dom = tl::parse(document, tl::ParserOptions::default()).unwrap();
+
+        let spans = link_spans(&dom, document);
+
+        assert_eq!(
+            vec!["/a", "b.png"],
+            spans
+                .iter()
+                .map(|span| &document[span.clone()])
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            r#"<a href="x">a</a><img src='y'><p title="c">"#,
+            replace(
+                document,
+                spans
+                    .into_iter()
+                    .zip(["x", "y"].map(String::from))
+                    .collect()
+            )
+        );
+    }
+}
@@ -0,0 +1,438 @@
+use std::{
+    fs::{read_to_string, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use chrono::DateTime;
+use parking_lot::Mutex;
+use reqwest::{cookie::CookieStore, header::HeaderValue, Url};
+
+/// Prefix of lines in cookies.txt holding `HttpOnly` cookies
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+/// Cookies shared by all workers, loaded from and saved to a Netscape
+/// cookies.txt file
+///
+/// The jar is the cookie store of the clients, so cookies set while following
+/// redirects are stored too.
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    cookies: Mutex<Vec<Cookie>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cookie {
+    /// Host the cookie belongs to, without a leading dot
+    domain: String,
+    /// Also send the cookie to subdomains
+    include_subdomains: bool,
+    path: String,
+    secure: bool,
+    http_only: bool,
+    /// Unix timestamp, session cookies have none
+    expires: Option<i64>,
+    name: String,
+    value: String,
+}
+
+impl Cookie {
+    fn matches(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default();
+
+        let domain_matches = host.eq_ignore_ascii_case(&self.domain)
+            || self.include_subdomains
+                && host.len() > self.domain.len()
+                && host.to_ascii_lowercase().ends_with(&self.domain)
+                && host[..host.len() - self.domain.len()].ends_with('.');
+
+        domain_matches
+            && path_matches(url.path(), &self.path)
+            && (!self.secure || url.scheme() == "https")
+    }
+
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires.map_or(false, |expires| expires <= now)
+    }
+
+    /// Parses a line of a cookies.txt file
+    fn parse_line(line: &str) -> Option<Self> {
+        let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+            Some(line) => (line, true),
+            None if line.starts_with('#') => return None,
+            None => (line, false),
+        };
+
+        let fields = line
+            .trim_end_matches(['\r', '\n'])
+            .split('\t')
+            .collect::<Vec<_>>();
+        let [domain, include_subdomains, path, secure, expires, name, value] = match fields[..] {
+            [a, b, c, d, e, f, g] => [a, b, c, d, e, f, g],
+            _ => return None,
+        };
+
+        Some(Self {
+            domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+            include_subdomains: include_subdomains.eq_ignore_ascii_case("TRUE"),
+            path: path.to_string(),
+            secure: secure.eq_ignore_ascii_case("TRUE"),
+            http_only,
+            expires: expires.parse().ok().filter(|expires| *expires != 0),
+            name: name.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    /// Parses a `Set-Cookie` header received from `url`
+    fn parse_set_cookie(header: &str, url: &Url, now: i64) -> Option<Self> {
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+
+        let mut cookie = Self {
+            domain: url.host_str()?.to_ascii_lowercase(),
+            include_subdomains: false,
+            path: default_path(url),
+            secure: false,
+            http_only: false,
+            expires: None,
+            name: name.trim().to_string(),
+            value: value.trim().to_string(),
+        };
+        if cookie.name.is_empty() {
+            return None;
+        }
+
+        let mut max_age = None;
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "domain" if !value.is_empty() => {
+                    let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                    // servers may only set cookies for themselves
+                    if domain == cookie.domain {
+                        // top-level domains and addresses have no subdomains
+                        // to share the cookie with
+                        cookie.include_subdomains = domain.contains('.') && url.domain().is_some();
+                        continue;
+                    }
+                    // and their parents, except top-level domains which would
+                    // share it with unrelated sites, suffixes like co.uk are
+                    // not known
+                    if url.domain().is_none()
+                        || !domain.contains('.')
+                        || !cookie.domain.ends_with(&format!(".{domain}"))
+                    {
+                        return None;
+                    }
+                    cookie.domain = domain;
+                    cookie.include_subdomains = true;
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "max-age" => max_age = value.parse::<i64>().ok(),
+                "expires" => {
+                    cookie.expires = DateTime::parse_from_rfc2822(value)
+                        .ok()
+                        .map(|expires| expires.timestamp())
+                        .or(cookie.expires)
+                }
+                _ => {}
+            }
+        }
+        // takes precedence over `Expires`
+        if let Some(max_age) = max_age {
+            cookie.expires = Some(now.saturating_add(max_age));
+        }
+
+        Some(cookie)
+    }
+}
+
+impl CookieJar {
+    /// Loads the cookies of a cookies.txt file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let cookies = read_to_string(path)?
+            .lines()
+            .filter_map(Cookie::parse_line)
+            .collect();
+
+        Ok(Self {
+            cookies: Mutex::new(cookies),
+        })
+    }
+
+    /// Writes the unexpired cookies to a cookies.txt file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let now = unix_now();
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writeln!(writer, "# Netscape HTTP Cookie File")?;
+        for cookie in self.cookies.lock().iter().filter(|c| !c.is_expired(now)) {
+            let bool_field = |value| if value { "TRUE" } else { "FALSE" };
+            writeln!(
+                writer,
+                "{}{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                if cookie.http_only {
+                    HTTP_ONLY_PREFIX
+                } else {
+                    ""
+                },
+                if cookie.include_subdomains { "." } else { "" },
+                cookie.domain,
+                bool_field(cookie.include_subdomains),
+                cookie.path,
+                bool_field(cookie.secure),
+                cookie.expires.unwrap_or(0),
+                cookie.name,
+                cookie.value,
+            )?;
+        }
+
+        writer.flush()
+    }
+
+    /// Value of the `Cookie` header for a request to `url`
+    pub fn header(&self, url: &Url) -> Option<String> {
+        let now = unix_now();
+
+        let header = self
+            .cookies
+            .lock()
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now) && cookie.matches(url))
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        Some(header).filter(|header| !header.is_empty())
+    }
+
+    /// Stores the cookies of the `Set-Cookie` headers of a response from `url`
+    pub fn store<'a, I>(&self, url: &Url, set_cookies: I)
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let now = unix_now();
+        let mut cookies = self.cookies.lock();
+
+        for cookie in set_cookies
+            .into_iter()
+            .filter_map(|header| Cookie::parse_set_cookie(header, url, now))
+        {
+            cookies.retain(|known| {
+                (&known.name, &known.domain, &known.path)
+                    != (&cookie.name, &cookie.domain, &cookie.path)
+            });
+            // expired cookies only delete the previous one
+            if !cookie.is_expired(now) {
+                cookies.push(cookie);
+            }
+        }
+    }
+}
+
+impl Clone for CookieJar {
+    /// A jar starting with the cookies of this one, which it does not share
+    /// afterwards
+    fn clone(&self) -> Self {
+        Self {
+            cookies: Mutex::new(self.cookies.lock().clone()),
+        }
+    }
+}
+
+impl CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        self.store(url, cookie_headers.filter_map(|value| value.to_str().ok()));
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        self.header(url)
+            .and_then(|header| HeaderValue::from_str(&header).ok())
+    }
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as i64)
+}
+
+/// Whether a cookie for `cookie_path` is sent to `path`, which it has to be a
+/// prefix of ending at a `/` as in RFC 6265
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    match path.strip_prefix(cookie_path) {
+        Some(rest) => rest.is_empty() || cookie_path.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Directory of the path of `url` as the default cookie path
+fn default_path(url: &Url) -> String {
+    match url.path().rsplit_once('/') {
+        Some(("", _)) | None => String::from("/"),
+        Some((directory, _)) => directory.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const COOKIES_TXT: &str = "# Netscape HTTP Cookie File
+.example.com\tTRUE\t/\tFALSE\t0\tsession\tabc
+#HttpOnly_wiki.example.com\tFALSE\t/private\tTRUE\t0\ttoken\tsecret
+example.com\tFALSE\t/\tFALSE\t1\texpired\tgone
+";
+
+    fn load() -> CookieJar {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cookies.txt");
+        std::fs::write(&path, COOKIES_TXT).unwrap();
+        CookieJar::load(&path).unwrap()
+    }
+
+    fn header(jar: &CookieJar, url: &str) -> Option<String> {
+        jar.header(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn cookies_txt() {
+        let jar = load();
+
+        assert_eq!(
+            Some(String::from("session=abc")),
+            header(&jar, "http://example.com/")
+        );
+        assert_eq!(
+            Some(String::from("session=abc; token=secret")),
+            header(&jar, "https://wiki.example.com/private/page")
+        );
+        assert_eq!(
+            Some(String::from("session=abc")),
+            header(&jar, "http://wiki.example.com/private/page")
+        );
+        assert_eq!(None, header(&jar, "http://notexample.com/"));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cookies.txt");
+
+        load().save(&path).unwrap();
+
+        assert_eq!(
+            "# Netscape HTTP Cookie File
+.example.com\tTRUE\t/\tFALSE\t0\tsession\tabc
+#HttpOnly_wiki.example.com\tFALSE\t/private\tTRUE\t0\ttoken\tsecret
+",
+            read_to_string(&path).unwrap()
+        );
+    }
+
+    #[test]
+    fn set_cookie() {
+        let jar = CookieJar::default();
+        let url = Url::parse("https://www.example.com/account/login").unwrap();
+
+        jar.store(
+            &url,
+            [
+                "a=1; Path=/; Domain=example.com",
+                "b=2",
+                "c=3; Domain=other.com",
+                "d=4; Max-Age=0",
+            ],
+        );
+
+        assert_eq!(
+            Some(String::from("a=1; b=2")),
+            header(&jar, "https://www.example.com/account/settings")
+        );
+        assert_eq!(
+            Some(String::from("a=1")),
+            header(&jar, "https://static.example.com/")
+        );
+
+        jar.store(&url, ["a=5; Path=/; Domain=example.com"]);
+        assert_eq!(
+            Some(String::from("a=5")),
+            header(&jar, "https://example.com/")
+        );
+    }
+
+    #[test]
+    fn top_level_domain() {
+        let jar = CookieJar::default();
+
+        jar.store(
+            &Url::parse("https://www.example.com/").unwrap(),
+            ["a=1; Domain=com", "b=2; Domain=.com"],
+        );
+        jar.store(
+            &Url::parse("http://localhost:8080/").unwrap(),
+            ["c=3; Domain=localhost"],
+        );
+        jar.store(
+            &Url::parse("http://127.0.0.1/").unwrap(),
+            ["d=4; Domain=0.0.1", "e=5; Domain=127.0.0.1"],
+        );
+
+        assert_eq!(None, header(&jar, "https://www.example.com/"));
+        assert_eq!(None, header(&jar, "https://other.com/"));
+        assert_eq!(Some(String::from("c=3")), header(&jar, "http://localhost/"));
+        assert_eq!(None, header(&jar, "http://www.localhost/"));
+        assert_eq!(Some(String::from("e=5")), header(&jar, "http://127.0.0.1/"));
+    }
+
+    #[test]
+    fn path() {
+        let jar = CookieJar::default();
+
+        jar.store(
+            &Url::parse("https://example.com/").unwrap(),
+            ["a=1; Path=/private", "b=2; Path=/docs/"],
+        );
+
+        assert_eq!(
+            Some(String::from("a=1")),
+            header(&jar, "https://example.com/private")
+        );
+        assert_eq!(
+            Some(String::from("a=1")),
+            header(&jar, "https://example.com/private/page")
+        );
+        assert_eq!(None, header(&jar, "https://example.com/privateer"));
+        assert_eq!(
+            Some(String::from("b=2")),
+            header(&jar, "https://example.com/docs/guide")
+        );
+        assert_eq!(None, header(&jar, "https://example.com/docs"));
+    }
+
+    #[test]
+    fn cloned_jar() {
+        let jar = CookieJar::default();
+        let url = Url::parse("https://example.com/").unwrap();
+        jar.store(&url, ["a=1"]);
+
+        let clone = jar.clone();
+        clone.store(&url, ["b=2"]);
+        jar.store(&url, ["c=3"]);
+
+        assert_eq!(
+            Some(String::from("a=1; c=3")),
+            header(&jar, "https://example.com/")
+        );
+        assert_eq!(
+            Some(String::from("a=1; b=2")),
+            header(&clone, "https://example.com/")
+        );
+    }
+}
@@ -0,0 +1,120 @@
+/// Extracts the targets of `@import` rules from a stylesheet.
+///
+/// Both `@import "file.css"` and `@import url(file.css)` are supported.
+pub fn imports(stylesheet: &str) -> Vec<String> {
+    let stylesheet = strip_comments(stylesheet);
+    let lowercase = stylesheet.to_ascii_lowercase();
+
+    lowercase
+        .match_indices("@import")
+        .filter_map(|(position, rule)| {
+            let rest = stylesheet[position + rule.len()..].trim_start();
+
+            if rest.len() >= 4 && rest[..4].eq_ignore_ascii_case("url(") {
+                url_function(&rest[4..])
+            } else {
+                quoted(rest)
+            }
+        })
+        .filter(|import| !import.is_empty() && !is_data_url(import))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Extracts the targets of all `url()` functions from a stylesheet, like
+/// background images and fonts, except `data:` urls
+pub fn urls(stylesheet: &str) -> Vec<String> {
+    let stylesheet = strip_comments(stylesheet);
+    let lowercase = stylesheet.to_ascii_lowercase();
+
+    lowercase
+        .match_indices("url(")
+        // skip functions whose name only ends in `url`
+        .filter(|(position, _)| {
+            !lowercase[..*position]
+                .ends_with(|c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .filter_map(|(position, function)| url_function(&stylesheet[position + function.len()..]))
+        .filter(|url| !url.is_empty() && !is_data_url(url))
+        .map(str::to_string)
+        .collect()
+}
+
+fn is_data_url(url: &str) -> bool {
+    url.len() >= 5 && url[..5].eq_ignore_ascii_case("data:")
+}
+
+/// Parses the argument of `url(` up to the closing parenthesis
+fn url_function(src: &str) -> Option<&str> {
+    let src = src.trim_start();
+
+    match quoted(src) {
+        Some(url) => Some(url),
+        None => src.split_once(')').map(|(url, _)| url.trim()),
+    }
+}
+
+/// Parses a single or double quoted string
+fn quoted(src: &str) -> Option<&str> {
+    let quote = src.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+
+    src[1..].split_once(quote).map(|(string, _)| string)
+}
+
+fn strip_comments(stylesheet: &str) -> String {
+    let mut stripped = String::with_capacity(stylesheet.len());
+    let mut rest = stylesheet;
+
+    while let Some((before, comment)) = rest.split_once("/*") {
+        stripped.push_str(before);
+        rest = comment.split_once("*/").map_or("", |(_, after)| after);
+    }
+    stripped.push_str(rest);
+
+    stripped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn import_forms() {
+        let stylesheet = r#"
+            @import "double.css";
+            @import 'single.css' screen;
+            @IMPORT url(unquoted.css);
+            @import url( "quoted-url.css" ) print;
+            body { color: red; }
+        "#;
+
+        assert_eq!(
+            vec!["double.css", "single.css", "unquoted.css", "quoted-url.css"],
+            imports(stylesheet)
+        );
+    }
+
+    #[test]
+    fn url_forms() {
+        let stylesheet = r#"
+            @import url("imported.css");
+            body { background: URL(bg.png) no-repeat; }
+            @font-face { src: url( 'font.woff2' ) format("woff2"), url("font.woff"); }
+            .icon { background-image: url(data:image/png;base64,iVBORw0KGgo=); }
+            /* .hidden { background: url(hidden.png); } */
+            .gradient { background: -webkit-image-set(myurl(nope.png) 1x); }
+        "#;
+
+        assert_eq!(
+            vec!["imported.css", "bg.png", "font.woff2", "font.woff"],
+            urls(stylesheet)
+        );
+    }
+
+    #[test]
+    fn commented_import() {
+        let stylesheet = r#"/* @import "hidden.css"; */ @import "visible.css";"#;
+
+        assert_eq!(vec!["visible.css"], imports(stylesheet));
+    }
+}
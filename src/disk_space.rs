@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use crate::{Error, Result};
+
+/// Fails with [`Error::InsufficientDiskSpace`] if the filesystem containing
+/// `path` has less than `min_free_space` bytes available.
+///
+/// `path` does not need to exist yet, the closest existing ancestor is queried
+/// instead.
+pub fn ensure_free_space(path: &Path, min_free_space: u64) -> Result<()> {
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or_else(|| Path::new("."));
+
+    let available = fs2::available_space(existing).map_err(Error::AvailableSpace)?;
+
+    check_free_space(available, min_free_space)
+}
+
+fn check_free_space(available: u64, min_free_space: u64) -> Result<()> {
+    if available < min_free_space {
+        Err(Error::InsufficientDiskSpace {
+            available,
+            min_free_space,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::check_free_space;
+    use crate::Error;
+
+    #[test]
+    fn below_threshold() {
+        assert!(matches!(
+            check_free_space(1024, 4096),
+            Err(Error::InsufficientDiskSpace {
+                available: 1024,
+                min_free_space: 4096
+            })
+        ));
+    }
+
+    #[test]
+    fn above_threshold() {
+        assert!(check_free_space(8192, 4096).is_ok());
+    }
+
+    #[test]
+    fn at_threshold() {
+        assert!(check_free_space(4096, 4096).is_ok());
+    }
+}
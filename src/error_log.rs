@@ -0,0 +1,82 @@
+use std::{io::Write, path::Path};
+
+use parking_lot::Mutex;
+use reqwest::{StatusCode, Url};
+
+use crate::{rotate::Rotation, Error, Result};
+
+/// Urls the server answered with an error status during a crawl
+#[derive(Debug, Default)]
+pub struct ErrorLog {
+    entries: Mutex<Vec<(Url, StatusCode)>>,
+}
+
+impl ErrorLog {
+    pub fn insert(&self, url: Url, status: StatusCode) {
+        self.entries.lock().push((url, status));
+    }
+
+    /// The entries sorted by url
+    pub fn entries(&self) -> Vec<(Url, StatusCode)> {
+        let mut entries = self.entries.lock().clone();
+        entries.sort();
+        entries
+    }
+
+    /// Appends a line with the status code and url of every entry to the file
+    /// at `path`, or the last one rotated out of it
+    pub fn append(&self, path: &Path, rotation: Rotation) -> Result<()> {
+        let mut writer = rotation.append(path).map_err(Error::CreateFile)?;
+
+        self.entries()
+            .iter()
+            .try_for_each(|(url, status)| writeln!(writer, "{} {url}", status.as_u16()))
+            .and_then(|_| writer.finish())
+            .map_err(Error::WriteFile)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::read_to_string;
+
+    use super::*;
+
+    #[test]
+    fn appends() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("errors.log");
+
+        let log = ErrorLog::default();
+        log.insert(
+            Url::parse("https://example.com/b").unwrap(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        );
+        log.insert(
+            Url::parse("https://example.com/a").unwrap(),
+            StatusCode::NOT_FOUND,
+        );
+        log.append(&path, Rotation::default()).unwrap();
+        log.append(&path, Rotation::default()).unwrap();
+
+        assert_eq!(
+            "404 https://example.com/a\n500 https://example.com/b\n".repeat(2),
+            read_to_string(&path).unwrap()
+        );
+
+        let rotation = Rotation {
+            max_bytes: Some(1),
+            gzip: false,
+        };
+        log.append(&path, rotation).unwrap();
+
+        assert_eq!(
+            "404 https://example.com/a\n",
+            read_to_string(dir.path().join("errors.log.1")).unwrap()
+        );
+        assert_eq!(
+            "500 https://example.com/b\n",
+            read_to_string(dir.path().join("errors.log.2")).unwrap()
+        );
+    }
+}
@@ -7,15 +7,25 @@ use std::{
 
 use self::char::CharExt;
 
+/// Separates file names from their query, `?` is reserved on Windows
+pub const QUERY_SEPARATOR: char = if cfg!(windows) { '\u{FF1F}' } else { '?' };
+
 pub trait EscapePathExt {
+    /// Escapes the characters which can not be part of a file name on the
+    /// current platform
     fn escape_path(&self) -> EscapePath;
+
+    /// Escapes the characters which can not be part of a file name on Windows
+    fn escape_reserved(&self) -> EscapePath;
 }
 
 impl EscapePathExt for String {
     fn escape_path(&self) -> EscapePath {
-        EscapePath {
-            inner: self.chars().flat_map(escape_path),
-        }
+        self.as_str().escape_path()
+    }
+
+    fn escape_reserved(&self) -> EscapePath {
+        self.as_str().escape_reserved()
     }
 }
 
@@ -25,15 +35,79 @@ impl EscapePathExt for str {
             inner: self.chars().flat_map(escape_path),
         }
     }
+
+    fn escape_reserved(&self) -> EscapePath {
+        EscapePath {
+            inner: self.chars().flat_map(escape_reserved),
+        }
+    }
 }
 
 impl<T> EscapePathExt for &T
 where
-    T: EscapePathExt,
+    T: EscapePathExt + ?Sized,
 {
     fn escape_path(&self) -> EscapePath {
         (*self).escape_path()
     }
+
+    fn escape_reserved(&self) -> EscapePath {
+        (*self).escape_reserved()
+    }
+}
+
+pub trait UnescapePathExt {
+    /// Reverses [`EscapePathExt::escape_path`] and
+    /// [`EscapePathExt::escape_reserved`], invalid escapes are kept as they are
+    fn unescape_path(&self) -> String;
+}
+
+impl UnescapePathExt for str {
+    fn unescape_path(&self) -> String {
+        let mut unescaped = String::with_capacity(self.len());
+        let mut rest = self;
+
+        while let Some(c) = rest.chars().next() {
+            let (c, len) = match c {
+                '\\' => match unescape_default(rest) {
+                    Some(unescaped) => unescaped,
+                    None => (c, 1),
+                },
+                c => (c.unescape_reserved(), c.len_utf8()),
+            };
+            unescaped.push(c);
+            rest = &rest[len..];
+        }
+
+        unescaped
+    }
+}
+
+impl UnescapePathExt for String {
+    fn unescape_path(&self) -> String {
+        self.as_str().unescape_path()
+    }
+}
+
+/// Decodes the escape of [`char::escape_default`] at the start of `src` and
+/// returns it with its length
+fn unescape_default(src: &str) -> Option<(char, usize)> {
+    let escaped = src.strip_prefix('\\')?;
+
+    let c = match escaped.chars().next()? {
+        't' => '\t',
+        'r' => '\r',
+        'n' => '\n',
+        c @ ('\\' | '\'' | '"') => c,
+        'u' => {
+            let (hex, _) = escaped.strip_prefix("u{")?.split_once('}')?;
+            let c = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)?;
+            return Some((c, "\\u{}".len() + hex.len()));
+        }
+        _ => return None,
+    };
+
+    Some((c, 2))
 }
 
 #[derive(Debug, Clone)]
@@ -87,6 +161,10 @@ fn escape_path(c: char) -> char::EscapePath {
     c.escape_path()
 }
 
+fn escape_reserved(c: char) -> char::EscapePath {
+    c.escape_reserved()
+}
+
 mod char {
     use std::{
         char::EscapeDefault,
@@ -197,24 +275,65 @@ mod char {
 
     pub(super) trait CharExt {
         fn escape_path(self) -> EscapePath;
+
+        fn escape_reserved(self) -> EscapePath;
+
+        fn unescape_reserved(self) -> char;
     }
 
     impl CharExt for char {
         fn escape_path(self) -> EscapePath {
+            if cfg!(windows) {
+                return self.escape_reserved();
+            }
+
+            let state = match self {
+                '/' => EscapePathState::Char('\u{2215}'),
+                _ => EscapePathState::Default(self.escape_default()),
+            };
+            EscapePath { state }
+        }
+
+        /// Maps the characters reserved on Windows to lookalikes like `/`
+        fn escape_reserved(self) -> EscapePath {
             let state = match self {
                 '/' => EscapePathState::Char('\u{2215}'),
+                '<' => EscapePathState::Char('\u{FF1C}'),
+                '>' => EscapePathState::Char('\u{FF1E}'),
+                ':' => EscapePathState::Char('\u{FE55}'),
+                '"' => EscapePathState::Char('\u{FF02}'),
+                '|' => EscapePathState::Char('\u{FF5C}'),
+                '?' => EscapePathState::Char('\u{FF1F}'),
+                '*' => EscapePathState::Char('\u{FF0A}'),
                 _ => EscapePathState::Default(self.escape_default()),
             };
             EscapePath { state }
         }
+
+        /// Maps the lookalikes of [`CharExt::escape_reserved`] back, other
+        /// characters which are not ASCII never appear unescaped
+        fn unescape_reserved(self) -> char {
+            match self {
+                '\u{2215}' => '/',
+                '\u{FF1C}' => '<',
+                '\u{FF1E}' => '>',
+                '\u{FE55}' => ':',
+                '\u{FF02}' => '"',
+                '\u{FF5C}' => '|',
+                '\u{FF1F}' => '?',
+                '\u{FF0A}' => '*',
+                c => c,
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::escape_path::EscapePathExt;
+    use crate::escape_path::{EscapePathExt, UnescapePathExt};
 
     #[test]
+    #[cfg(not(windows))]
     fn escape_path_url() {
         assert_eq!(
             "https:\u{2215}\u{2215}www.google.com\u{2215}&ec=GAZAAQ".to_string(),
@@ -223,4 +342,48 @@ mod test {
                 .collect::<String>()
         );
     }
+
+    #[test]
+    fn escape_reserved_url() {
+        for (reserved, lookalike) in [
+            ('<', '\u{FF1C}'),
+            ('>', '\u{FF1E}'),
+            (':', '\u{FE55}'),
+            ('"', '\u{FF02}'),
+            ('|', '\u{FF5C}'),
+            ('?', '\u{FF1F}'),
+            ('*', '\u{FF0A}'),
+        ] {
+            assert_eq!(
+                format!("https{lookalike}\u{2215}\u{2215}www.google.com\u{2215}&ec=GAZAAQ"),
+                format!("https{reserved}//www.google.com/&ec=GAZAAQ")
+                    .escape_reserved()
+                    .collect::<String>()
+            );
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let samples = [
+            "https://www.google.com/&ec=GAZAAQ",
+            "a\\b'c\"d\te\r\nf",
+            "<>:\"|?*",
+            "caf\u{e9} \u{1F600} \u{0} \u{7f}",
+            // lookalikes and escapes in the original survive
+            "\u{2215}\u{FF1F}\\u{41}\\",
+        ];
+        // every character of the first planes
+        let all = (0..0x3000).filter_map(char::from_u32).collect::<String>();
+
+        for s in samples.iter().copied().chain([all.as_str()]) {
+            assert_eq!(s, s.escape_path().to_string().unescape_path());
+            assert_eq!(s, s.escape_reserved().to_string().unescape_path());
+        }
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        assert_eq!("\\x \\u{zz} \\", "\\x \\u{zz} \\".unescape_path());
+    }
 }
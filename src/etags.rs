@@ -0,0 +1,83 @@
+use std::{
+    collections::BTreeMap,
+    fs::{read_to_string, File},
+    io::{BufWriter, ErrorKind, Write},
+    path::Path,
+};
+
+use dashmap::DashMap;
+use reqwest::Url;
+
+use crate::{Error, Result};
+
+/// Name of the sidecar file holding the ETags in the output directory
+pub const ETAGS_FILE_NAME: &str = ".wmt-etags.json";
+
+/// ETags of the saved files by the url they were downloaded from
+#[derive(Debug, Default)]
+pub struct ETags {
+    etags: DashMap<Url, String>,
+}
+
+impl ETags {
+    /// Loads the ETags of earlier crawls in `output_path`, if any
+    pub fn load(output_path: &Path) -> Result<Self> {
+        let etags = match read_to_string(output_path.join(ETAGS_FILE_NAME)) {
+            Ok(json) => serde_json::from_str::<BTreeMap<String, String>>(&json)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|(url, etag)| Some((Url::parse(&url).ok()?, etag)))
+                .collect(),
+            Err(err) if err.kind() == ErrorKind::NotFound => DashMap::new(),
+            Err(err) => return Err(Error::ReadFile(err)),
+        };
+
+        Ok(Self { etags })
+    }
+
+    pub fn get(&self, url: &Url) -> Option<String> {
+        self.etags.get(url).map(|etag| etag.clone())
+    }
+
+    pub fn insert(&self, url: Url, etag: String) {
+        self.etags.insert(url, etag);
+    }
+
+    /// Writes the ETags to the sidecar file in `output_path`
+    pub fn save(&self, output_path: &Path) -> Result<()> {
+        let etags = self
+            .etags
+            .iter()
+            .map(|entry| (entry.key().to_string(), entry.value().clone()))
+            .collect::<BTreeMap<_, _>>();
+
+        let file = File::create(output_path.join(ETAGS_FILE_NAME)).map_err(Error::CreateFile)?;
+        let mut writer = BufWriter::new(file);
+
+        serde_json::to_writer_pretty(&mut writer, &etags)
+            .map_err(|err| Error::WriteFile(err.into()))?;
+        writer.flush().map_err(Error::WriteFile)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let output = tempfile::tempdir().unwrap();
+        let url = Url::parse("https://example.com/style.css").unwrap();
+
+        let etags = ETags::load(output.path()).unwrap();
+        assert_eq!(None, etags.get(&url));
+
+        etags.insert(url.clone(), String::from("\"33a64df5\""));
+        etags.save(output.path()).unwrap();
+
+        assert_eq!(
+            Some(String::from("\"33a64df5\"")),
+            ETags::load(output.path()).unwrap().get(&url)
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::sitemap::unescape;
+
+lazy_static! {
+    /// `<link>` of RSS items and `<link href>` of Atom entries
+    static ref LINK: Regex =
+        Regex::new(r"(?is)<(?:[\w-]+:)?link\b([^>]*?)(?:/>|>\s*(.*?)\s*</(?:[\w-]+:)?link>)")
+            .unwrap();
+    static ref ENCLOSURE: Regex = Regex::new(r"(?is)<enclosure\b([^>]*)>").unwrap();
+}
+
+/// MIME types of RSS and Atom feeds
+const FEED_TYPES: [&str; 3] = [
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/xml",
+];
+
+/// Whether the MIME type `essence` denotes a feed
+pub fn is_feed(essence: &str) -> bool {
+    FEED_TYPES
+        .iter()
+        .any(|feed_type| feed_type.eq_ignore_ascii_case(essence))
+}
+
+/// Urls of the `<link>` and `<enclosure>` elements of an RSS or Atom feed
+pub fn links(feed: &str) -> Vec<String> {
+    let links = LINK.captures_iter(feed).filter_map(|captures| {
+        captures
+            .get(2)
+            .map(|text| {
+                text.as_str()
+                    .trim_start_matches("<![CDATA[")
+                    .trim_end_matches("]]>")
+                    .to_string()
+            })
+            .filter(|text| !text.is_empty())
+            .or_else(|| attribute(&captures[1], "href"))
+    });
+    let enclosures = ENCLOSURE
+        .captures_iter(feed)
+        .filter_map(|captures| attribute(&captures[1], "url"));
+
+    links
+        .chain(enclosures)
+        .map(|link| unescape(link.trim()))
+        .filter(|link| !link.is_empty())
+        .collect()
+}
+
+/// Value of the attribute `name` in the attributes of a tag
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    attributes
+        .split_whitespace()
+        .filter_map(|attribute| attribute.split_once('='))
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| {
+            value
+                .trim_end_matches('/')
+                .trim_matches(|c| c == '"' || c == '\'')
+                .to_string()
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rss() {
+        let feed = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
+  <channel>
+    <link>https://example.com/</link>
+    <atom:link href="https://example.com/feed.xml" rel="self"/>
+    <item>
+      <link>
+        https://example.com/episodes?id=1&amp;lang=en
+      </link>
+      <enclosure url="https://cdn.example.com/1.mp3" length="1024" type="audio/mpeg"/>
+    </item>
+    <item>
+      <LINK><![CDATA[/episodes/2]]></LINK>
+      <enclosure type="audio/mpeg" url='/2.mp3' />
+    </item>
+  </channel>
+</rss>"#;
+
+        assert_eq!(
+            vec![
+                "https://example.com/",
+                "https://example.com/feed.xml",
+                "https://example.com/episodes?id=1&lang=en",
+                "/episodes/2",
+                "https://cdn.example.com/1.mp3",
+                "/2.mp3",
+            ],
+            links(feed)
+        );
+    }
+
+    #[test]
+    fn atom() {
+        let feed = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+  <link href="https://example.com/"/>
+  <entry>
+    <link rel="alternate" href="/posts/hello"/>
+    <link rel="enclosure" href="/hello.mp4" type="video/mp4"></link>
+  </entry>
+</feed>"#;
+
+        assert_eq!(
+            vec!["https://example.com/", "/posts/hello", "/hello.mp4"],
+            links(feed)
+        );
+    }
+
+    #[test]
+    fn feed_types() {
+        assert!(is_feed("application/rss+xml"));
+        assert!(is_feed("Application/Atom+XML"));
+        assert!(is_feed("application/xml"));
+        assert!(!is_feed("text/html"));
+    }
+}
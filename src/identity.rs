@@ -0,0 +1,194 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client, ClientBuilder, Proxy, Url,
+};
+
+/// Headers and proxy of one account used for crawling
+///
+/// The client of every identity has its own cookies, see [`ClientPool::new`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Identity {
+    /// Headers sent with every request, e.g. `Cookie` or `Authorization`
+    pub headers: Vec<(String, String)>,
+    /// Proxy all requests of this identity go through
+    pub proxy: Option<String>,
+}
+
+impl Identity {
+    /// Configures `builder` to send requests as this identity
+    pub fn apply(&self, builder: ClientBuilder) -> reqwest::Result<ClientBuilder> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            // validated when parsing
+            if let (Ok(name), Ok(value)) =
+                (HeaderName::from_str(name), HeaderValue::from_str(value))
+            {
+                headers.append(name, value);
+            }
+        }
+
+        let builder = builder.default_headers(headers);
+
+        match &self.proxy {
+            // replaces the proxy of the settings
+            Some(proxy) => Ok(builder.no_proxy().proxy(Proxy::all(proxy)?)),
+            None => Ok(builder),
+        }
+    }
+}
+
+impl FromStr for Identity {
+    type Err = String;
+
+    /// Parses `;` separated `name=value` pairs where `proxy` sets the proxy and
+    /// every other name a header
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut identity = Self::default();
+
+        for pair in s.split(';').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected `name=value`, got `{pair}`"))?;
+            let (name, value) = (name.trim(), value.trim());
+
+            if name.eq_ignore_ascii_case("proxy") {
+                Proxy::all(value).map_err(|err| format!("invalid proxy `{value}`: {err}"))?;
+                identity.proxy = Some(value.to_string());
+                continue;
+            }
+
+            HeaderName::from_str(name).map_err(|err| format!("invalid header `{name}`: {err}"))?;
+            HeaderValue::from_str(value)
+                .map_err(|err| format!("invalid value for `{name}`: {err}"))?;
+            identity.headers.push((name.to_string(), value.to_string()));
+        }
+
+        Ok(identity)
+    }
+}
+
+/// How requests are spread over the identities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentitySelection {
+    /// Use the identities in turn
+    RoundRobin,
+    /// Always use the same identity for a host
+    StickyPerHost,
+}
+
+impl Default for IdentitySelection {
+    fn default() -> Self {
+        Self::RoundRobin
+    }
+}
+
+impl FromStr for IdentitySelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "round-robin" => Ok(Self::RoundRobin),
+            "sticky" => Ok(Self::StickyPerHost),
+            _ => Err(format!("expected `round-robin` or `sticky`, got `{s}`")),
+        }
+    }
+}
+
+/// One client per identity shared by all workers
+#[derive(Debug)]
+pub struct ClientPool {
+    clients: Vec<Client>,
+    selection: IdentitySelection,
+    next: AtomicUsize,
+}
+
+impl ClientPool {
+    /// Builds a client for every identity on top of `builder`
+    ///
+    /// `builder` is called once per identity, so it should set up a separate
+    /// cookie store each time to keep the sessions of the identities apart.
+    pub fn new<F>(
+        identities: &[Identity],
+        selection: IdentitySelection,
+        builder: F,
+    ) -> reqwest::Result<Self>
+    where
+        F: Fn() -> ClientBuilder,
+    {
+        let clients = identities
+            .iter()
+            .map(|identity| identity.apply(builder())?.build())
+            .collect::<reqwest::Result<_>>()?;
+
+        Ok(Self {
+            clients,
+            selection,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// The client to request `url` with, if there are any identities
+    pub fn select(&self, url: &Url) -> Option<&Client> {
+        if self.clients.is_empty() {
+            return None;
+        }
+
+        let index = match self.selection {
+            IdentitySelection::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed),
+            IdentitySelection::StickyPerHost => {
+                let mut hasher = DefaultHasher::new();
+                url.host_str().hash(&mut hasher);
+                hasher.finish() as usize
+            }
+        };
+
+        self.clients.get(index % self.clients.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_identity() {
+        assert_eq!(
+            Ok(Identity {
+                headers: vec![(String::from("Cookie"), String::from("session=abc"))],
+                proxy: Some(String::from("http://127.0.0.1:3128")),
+            }),
+            "Cookie=session=abc; proxy=http://127.0.0.1:3128".parse()
+        );
+        assert!("Cookie".parse::<Identity>().is_err());
+        assert!("Bad Header=1".parse::<Identity>().is_err());
+        assert!("proxy=http://[x".parse::<Identity>().is_err());
+    }
+
+    #[test]
+    fn sticky_per_host() {
+        let identities = vec![Identity::default(); 3];
+        let pool = ClientPool::new(
+            &identities,
+            IdentitySelection::StickyPerHost,
+            Client::builder,
+        )
+        .unwrap();
+        let url = Url::parse("https://example.com/").unwrap();
+
+        let first = pool.select(&url).map(|client| client as *const Client);
+
+        for _ in 0..5 {
+            assert_eq!(
+                first,
+                pool.select(&url).map(|client| client as *const Client)
+            );
+        }
+    }
+}
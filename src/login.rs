@@ -0,0 +1,121 @@
+use reqwest::{Client, Url};
+
+use crate::{Error, Result};
+
+/// Login form posted before the crawl, its session cookies authorize the
+/// requests of the workers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginConfig {
+    pub url: Url,
+    /// Fields of the form in order
+    pub fields: Vec<(String, String)>,
+}
+
+impl LoginConfig {
+    /// Posts the form with `client`, whose cookie store keeps the session
+    pub async fn log_in(&self, client: &Client) -> Result<()> {
+        let res = client
+            .post(self.url.clone())
+            .form(&self.fields)
+            .send()
+            .await
+            .map_err(Error::SendRequest)?;
+
+        let status = res.status();
+        if status.is_client_error() || status.is_server_error() {
+            return Err(Error::Login { status });
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a `name=value` form field
+pub fn parse_field(src: &str) -> std::result::Result<(String, String), String> {
+    match src.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("expected `name=value`, got `{src}`")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        cookies::CookieJar,
+        test_server::{Response, TestServer},
+    };
+
+    fn login(server: &TestServer) -> LoginConfig {
+        LoginConfig {
+            url: Url::parse(&format!("http://localhost:{}/login", server.port())).unwrap(),
+            fields: vec![
+                (String::from("user"), String::from("me")),
+                (String::from("password"), String::from("p&ss")),
+            ],
+        }
+    }
+
+    fn log_in(login: &LoginConfig, cookie_jar: &Arc<CookieJar>) -> Result<()> {
+        let client = Client::builder()
+            .cookie_provider(cookie_jar.clone())
+            .build()
+            .unwrap();
+
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(login.log_in(&client))
+    }
+
+    #[test]
+    fn session_cookie() {
+        let server =
+            TestServer::start(
+                |request| match (request.method.as_str(), request.path.as_str()) {
+                    ("POST", "/login") if request.body == b"user=me&password=p%26ss" => {
+                        Response::status(302)
+                            .header("Location", "/account")
+                            .header("Set-Cookie", "session=abc; Path=/")
+                    }
+                    ("GET", "/account") if request.header("Cookie") == Some("session=abc") => {
+                        Response::ok("welcome")
+                    }
+                    _ => Response::status(403),
+                },
+            );
+        let login = login(&server);
+        let cookie_jar = Arc::new(CookieJar::default());
+
+        log_in(&login, &cookie_jar).unwrap();
+
+        assert_eq!(
+            Some(String::from("session=abc")),
+            cookie_jar.header(&login.url.join("/account").unwrap())
+        );
+    }
+
+    #[test]
+    fn rejected() {
+        let server = TestServer::start(|_| Response::status(403));
+        let login = login(&server);
+
+        assert!(matches!(
+            log_in(&login, &Arc::new(CookieJar::default())),
+            Err(Error::Login { status }) if status.as_u16() == 403
+        ));
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(
+            Ok((String::from("token"), String::from("a=b"))),
+            parse_field("token=a=b")
+        );
+        assert!(parse_field("=value").is_err());
+        assert!(parse_field("name").is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::BTreeMap,
+    fs::{read_to_string, File},
+    io::{BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use dashmap::DashMap;
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+
+use crate::{Error, Result};
+
+/// Name of the manifest of hashed paths in the output directory
+pub const MANIFEST_FILE_NAME: &str = "HASHED_PATHS";
+
+/// Directory below the host directory containing files with hashed paths
+const HASHED_DIR: &str = "_hashed";
+
+/// Longest path the platform can open
+#[cfg(windows)]
+const MAX_PATH_LEN: usize = 260;
+#[cfg(not(windows))]
+const MAX_PATH_LEN: usize = 4096;
+
+/// Longest file or directory name most filesystems support
+pub const MAX_NAME_LEN: usize = 255;
+
+/// Longest extension kept for hashed file names
+const MAX_EXTENSION_LEN: usize = 16;
+
+/// Replaces `path` with a path of bounded length if saving it below
+/// `output_path` would exceed the limits of the platform.
+///
+/// The first component of `path` is the host and is kept as is.
+pub fn bound_path(output_path: &Path, path: &Path) -> Option<PathBuf> {
+    let too_long = output_path.join(path).as_os_str().len() > MAX_PATH_LEN
+        || path
+            .components()
+            .any(|component| component.as_os_str().len() > MAX_NAME_LEN);
+
+    if !too_long {
+        return None;
+    }
+
+    let mut components = path.components();
+    let host = components.next()?;
+    let rest = components.as_path();
+
+    let mut file_name = format!(
+        "{:x}",
+        Sha256::digest(rest.as_os_str().to_string_lossy().as_bytes())
+    );
+    if let Some(extension) = rest
+        .extension()
+        .map(|extension| extension.to_string_lossy())
+        .filter(|extension| extension.len() <= MAX_EXTENSION_LEN)
+    {
+        file_name = format!("{file_name}.{extension}");
+    }
+
+    Some(Path::new(&host).join(HASHED_DIR).join(file_name))
+}
+
+/// Urls of files saved with hashed paths by their path relative to the output directory
+#[derive(Debug, Default)]
+pub struct HashedPaths {
+    paths: DashMap<PathBuf, Url>,
+}
+
+impl HashedPaths {
+    pub fn insert(&self, path: PathBuf, url: Url) {
+        self.paths.insert(path, url);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Adds the hashed paths to the manifest in `output_path`, keeping the
+    /// entries of earlier crawls
+    pub fn write_manifest(&self, output_path: &Path) -> Result<()> {
+        let manifest_path = output_path.join(MANIFEST_FILE_NAME);
+
+        let mut entries = match read_to_string(&manifest_path) {
+            Ok(manifest) => manifest
+                .lines()
+                .filter_map(|line| line.split_once("  "))
+                .map(|(path, url)| (path.to_string(), url.to_string()))
+                .collect(),
+            Err(err) if err.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(Error::ReadFile(err)),
+        };
+        for entry in self.paths.iter() {
+            entries.insert(entry.key().display().to_string(), entry.value().to_string());
+        }
+
+        let file = File::create(manifest_path).map_err(Error::CreateFile)?;
+        let mut writer = BufWriter::new(file);
+
+        entries
+            .iter()
+            .try_for_each(|(path, url)| writeln!(writer, "{path}  {url}"))
+            .and_then(|_| writer.flush())
+            .map_err(Error::WriteFile)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn short_path() {
+        assert_eq!(
+            None,
+            bound_path(Path::new("."), Path::new("example.com/index.html"))
+        );
+    }
+
+    #[test]
+    fn long_file_name() {
+        let path = PathBuf::from(format!("example.com/docs/{}.pdf", "a".repeat(300)));
+
+        let bounded = bound_path(Path::new("."), &path).unwrap();
+
+        assert!(bounded.starts_with("example.com/_hashed"));
+        assert_eq!(Some("pdf".as_ref()), bounded.extension());
+        assert!(bounded.file_name().unwrap().len() <= MAX_NAME_LEN);
+    }
+
+    #[test]
+    fn deep_path() {
+        let path = PathBuf::from(format!("example.com/{}index.html", "segment/".repeat(600)));
+
+        let bounded = bound_path(Path::new("."), &path).unwrap();
+
+        assert!(bounded.as_os_str().len() < 100);
+    }
+
+    #[test]
+    fn manifest_keeps_earlier_entries() {
+        let output = tempfile::tempdir().unwrap();
+
+        let first = HashedPaths::default();
+        first.insert(
+            PathBuf::from("example.com/_hashed/b"),
+            Url::parse("https://example.com/b").unwrap(),
+        );
+        first.write_manifest(output.path()).unwrap();
+
+        let second = HashedPaths::default();
+        second.insert(
+            PathBuf::from("example.com/_hashed/a"),
+            Url::parse("https://example.com/a").unwrap(),
+        );
+        second.write_manifest(output.path()).unwrap();
+
+        assert_eq!(
+            "example.com/_hashed/a  https://example.com/a\nexample.com/_hashed/b  https://example.com/b\n",
+            read_to_string(output.path().join(MANIFEST_FILE_NAME)).unwrap()
+        );
+    }
+}
@@ -1,26 +1,64 @@
 #![feature(iterator_try_collect, result_option_inspect)]
 
 use std::{
+    ffi::OsString,
+    iter,
+    net::{IpAddr, SocketAddr, TcpListener},
+    num::NonZeroUsize,
     path::{Path, PathBuf},
     sync::Arc,
     thread,
+    time::{Duration, Instant},
 };
 
-use clap::{IntoApp, Parser};
+use chrono::{DateTime, Utc};
+use clap::{ArgMatches, FromArgMatches, IntoApp, Parser, Subcommand};
 use console::style;
 use dashmap::DashSet;
-use indicatif::{MultiProgress, ProgressBar};
-use reqwest::{Client, Url};
-use synchronoise::CountdownEvent;
+use indicatif::{HumanBytes, HumanDuration, MultiProgress, ProgressBar, ProgressDrawTarget};
+use log::{warn, LevelFilter};
+use regex::Regex;
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    ClientBuilder, Proxy, Url,
+};
+use tokio::runtime::{Builder as RuntimeBuilder, Runtime};
 use walkdir::WalkDir;
-use wmt::{priority_queue::PriorityQueue, progress_style, Settings, Worker};
+use wmt::{
+    busy::BusyWorkers,
+    checksums::Checksums,
+    config::{Config, OptionValue},
+    cookies::CookieJar,
+    etags::ETags,
+    identity::{ClientPool, Identity, IdentitySelection},
+    login::{self, LoginConfig},
+    long_paths,
+    metrics::{self, CrawlStats},
+    observer::LogObserver,
+    partial::{handle_partial_files, is_partial_file, OnPartial},
+    priority_queue::{PriorityQueue, DEFAULT_SPILL_CAPACITY},
+    progress_style, relayout,
+    resume::CrawlSnapshot,
+    serve,
+    state::State,
+    strip_type_extension,
+    visited::{self, DiskStore, VisitedStore},
+    warc::{WarcWriter, WARC_FILE_NAME},
+    OutputFormat, QueryLayout, Settings, Worker, DEFAULT_ACCEPT,
+};
+
+/// Time between writes of the state file during a crawl
+const STATE_SAVE_INTERVAL: Duration = Duration::from_secs(30);
 
 static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
 
 /// Recursively download a website
 #[derive(Parser, Debug)]
-#[clap(author, version, about, long_about = None)]
+#[clap(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// Target URLs to start from
     #[clap(parse(try_from_str), value_name = "URL")]
     targets: Vec<Url>,
@@ -29,106 +67,1134 @@ struct Args {
     #[clap(short, long, parse(from_os_str), default_value = ".")]
     output: PathBuf,
 
+    /// Read options from this TOML file, keyed by their long flag names and
+    /// `targets`. Flags given here take precedence
+    #[clap(long, parse(from_os_str), value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Archive whole sites, same as `--convert-links --incremental` without a
+    /// depth limit unless `--depth` is given. Assets of pages are always saved
+    #[clap(short, long)]
+    mirror: bool,
+
     /// Show progress
     // #[clap(short, long)]
     // progress: bool,
 
-    /// How many threads to use
-    #[clap(short, long, default_value_t = num_cpus::get())]
+    /// Only print errors, without progress bars
+    #[clap(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log every download instead of showing progress bars, twice to also log
+    /// the queue and status codes
+    #[clap(short, long, parse(from_occurrences))]
+    verbose: u64,
+
+    /// How many downloads to run concurrently, sharing one thread per CPU
+    #[clap(short, long, default_value_t = num_cpus::get(), parse(try_from_str = parse_threads))]
     threads: usize,
+
+    /// Stop once the output filesystem has less than this many bytes free
+    #[clap(long, value_name = "BYTES")]
+    min_free_space: Option<u64>,
+
+    /// Resolve a host to the given address instead of asking DNS
+    #[clap(long = "resolve", parse(try_from_str = parse_host_override), value_name = "HOST:IP")]
+    host_overrides: Vec<(String, IpAddr)>,
+
+    /// Re-encode HTML documents served in legacy charsets as UTF-8
+    #[clap(long)]
+    normalize_charset: bool,
+
+    /// Spread the start of the workers over this many milliseconds
+    #[clap(long, value_name = "MILLIS")]
+    ramp_up: Option<u64>,
+
+    /// Also follow links from Open Graph `<meta>` tags and JSON-LD blocks
+    #[clap(long)]
+    follow_metadata: bool,
+
+    /// Allow links to this non-standard port
+    #[clap(long = "allow-port", value_name = "PORT")]
+    allowed_ports: Vec<u16>,
+
+    /// Drop links to hosts resolving to loopback, link-local or private addresses
+    /// and refuse to connect to them, redirects included
+    #[clap(long)]
+    block_private_addresses: bool,
+
+    /// Allow this host to resolve to a private address despite --block-private-addresses
+    #[clap(long = "allow-private-host", value_name = "HOST")]
+    allowed_private_hosts: Vec<String>,
+
+    /// Write a SHA256SUMS manifest of all saved files to the output path
+    #[clap(long)]
+    checksums: bool,
+
+    /// What to do with partial downloads of an interrupted run (resume or discard),
+    /// resumed downloads request only the missing bytes
+    #[clap(long, default_value = "discard", value_name = "POLICY")]
+    on_partial: OnPartial,
+
+    /// Follow at most this many levels of nested stylesheet `@import` rules
+    #[clap(long, value_name = "DEPTH")]
+    max_css_depth: Option<usize>,
+
+    /// Treat urls differing only in percent-encoding (like `%7E` and `~`) as the same
+    #[clap(long)]
+    normalize_percent_encoding: bool,
+
+    /// Treat urls differing only in the order of query parameters as the same
+    #[clap(long)]
+    sort_query: bool,
+
+    /// Serve Prometheus metrics of the crawl on this address
+    #[clap(long, value_name = "ADDR")]
+    metrics: Option<SocketAddr>,
+
+    /// Where to save the query of urls ending in a slash (file or directory)
+    #[clap(long, default_value = "file", value_name = "LAYOUT")]
+    query_layout: QueryLayout,
+
+    /// Save the files of a single target host directly in the output path, without a host directory
+    #[clap(long)]
+    flat: bool,
+
+    /// Save loose files or append requests and responses to `crawl.warc` in the output path (files or warc)
+    #[clap(long, default_value = "files", value_name = "FORMAT")]
+    output_format: OutputFormat,
+
+    /// Only this query parameter tells resources apart, others are dropped (`name*` matches a prefix)
+    #[clap(long = "significant-param", value_name = "NAME")]
+    significant_params: Vec<String>,
+
+    /// Drop the query of urls, so `page?utm=1` and `page?utm=2` are requested and saved once as `page`
+    #[clap(long)]
+    ignore_query: bool,
+
+    /// Spread requests over this identity given as `;` separated `header=value` pairs and an optional `proxy=URL`.
+    /// Every identity has its own cookies, only those without an identity are saved to `--cookies`
+    #[clap(long = "identity", value_name = "IDENTITY")]
+    identities: Vec<Identity>,
+
+    /// How requests are assigned to identities (round-robin or sticky)
+    #[clap(long, default_value = "round-robin", value_name = "SELECTION")]
+    identity_selection: IdentitySelection,
+
+    /// Abort the crawl after this many failed downloads
+    #[clap(long, value_name = "COUNT")]
+    max_errors: Option<usize>,
+
+    /// Use the ligatures of the Fira Code font for spinners
+    #[clap(long)]
+    fira_code: bool,
+
+    /// Characters of the spinner animation, the last one is shown when finished
+    #[clap(long, value_name = "CHARS")]
+    tick_chars: Option<String>,
+
+    /// indicatif template of the spinners
+    #[clap(long, value_name = "TEMPLATE")]
+    spinner_template: Option<String>,
+
+    /// indicatif template of the download progress bars
+    #[clap(long, value_name = "TEMPLATE")]
+    bar_template: Option<String>,
+
+    /// indicatif template of the progress bar of the whole crawl
+    #[clap(long, value_name = "TEMPLATE")]
+    total_template: Option<String>,
+
+    /// Only report which links of the target pages are in scope and why, without crawling
+    #[clap(long)]
+    scope_report: bool,
+
+    /// Keep the visited urls in an on-disk store in this directory instead of in memory
+    #[clap(long, parse(from_os_str), value_name = "PATH")]
+    visited_store: Option<PathBuf>,
+
+    /// `Accept` header sent with every request
+    #[clap(long, parse(try_from_str = HeaderValue::from_str), default_value = DEFAULT_ACCEPT)]
+    accept: HeaderValue,
+
+    /// Spill queued urls exceeding the in-memory capacity to this directory
+    #[clap(long, parse(from_os_str), value_name = "DIR")]
+    spill_queue: Option<PathBuf>,
+
+    /// Stop downloading from a host once this many bytes were saved from it
+    #[clap(long, value_name = "BYTES")]
+    per_host_byte_budget: Option<u64>,
+
+    /// Download at most this many urls of the same host at once
+    #[clap(long, value_name = "N")]
+    max_per_host: Option<NonZeroUsize>,
+
+    /// Don't save pages marked noindex and don't follow links of pages marked nofollow
+    #[clap(long)]
+    skip_noindex: bool,
+
+    /// Spread the saved files over hash-prefixed directories (ab/cd/<path>)
+    #[clap(long)]
+    shard_output: bool,
+
+    /// Only save and follow pages modified after this RFC 3339 date (e.g. 2022-01-31T00:00:00Z)
+    #[clap(long, parse(try_from_str = parse_rfc3339), value_name = "DATE")]
+    modified_since: Option<DateTime<Utc>>,
+
+    /// Only download files already saved to the output path again if they were
+    /// modified since, following the links of the saved copy otherwise
+    #[clap(long)]
+    incremental: bool,
+
+    /// Never download files already saved to the output path again, following
+    /// the links of the saved copy instead
+    #[clap(long)]
+    no_clobber: bool,
+
+    /// Replace saved files identical to an earlier one, except pages, with a hard link to it
+    #[clap(long)]
+    dedup_content: bool,
+
+    /// Follow redirects leaving the hosts of the targets
+    #[clap(long)]
+    follow_offsite_redirects: bool,
+
+    /// Also crawl subdomains of the target hosts, like `cdn.example.com`
+    #[clap(long)]
+    span_subdomains: bool,
+
+    /// Crawl links to any host, best combined with `--depth`
+    #[clap(long)]
+    span_hosts: bool,
+
+    /// Write an nginx map from the original urls to the saved files
+    #[clap(long)]
+    rewrite_map: bool,
+
+    /// Skip files larger than this size, like `500M`
+    #[clap(long = "max-size", parse(try_from_str = parse_byte_size), value_name = "SIZE")]
+    max_file_size: Option<u64>,
+
+    /// Only list the urls which would be saved and their sizes
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Write a JSON manifest of the saved files to this path
+    #[clap(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// Save the bodies of 4xx and 5xx responses instead of skipping them
+    #[clap(long)]
+    save_error_pages: bool,
+
+    /// Append the status code and url of every 4xx and 5xx response to this file
+    #[clap(long, value_name = "FILE")]
+    log_errors: Option<PathBuf>,
+
+    /// Continue the checksum manifest and the error log in a numbered file after this many bytes
+    #[clap(long, value_name = "BYTES")]
+    log_rotate_bytes: Option<u64>,
+
+    /// Compress the checksum manifest, the JSON manifest and the error log with gzip
+    #[clap(long)]
+    compress_logs: bool,
+
+    /// Copy the mirror in this directory to the layout of the other options
+    /// instead of crawling, needs a mirror saved with `--rewrite-map`
+    #[clap(long, value_name = "DIR")]
+    relayout_from: Option<PathBuf>,
+
+    /// Point the links of saved pages to the saved files for offline browsing
+    #[clap(long)]
+    convert_links: bool,
+
+    /// Also download urls disallowed by robots.txt
+    #[clap(long)]
+    ignore_robots: bool,
+
+    /// Also crawl the urls listed by the sitemap.xml of every target host
+    #[clap(long)]
+    sitemap: bool,
+
+    /// Wait this many milliseconds between two requests to the same host
+    #[clap(long = "delay", value_name = "MILLIS")]
+    request_delay: Option<u64>,
+
+    /// Wait between 0.5 and 1.5 times the delay instead, picked for every request
+    #[clap(long)]
+    random_wait: bool,
+
+    /// Follow at most this many links from the targets
+    #[clap(long = "depth")]
+    max_depth: Option<usize>,
+
+    /// Only save files with these extensions, pages are still crawled for links
+    #[clap(long = "accept-ext", use_value_delimiter = true, parse(from_str = parse_extension), value_name = "EXTENSIONS")]
+    accept_extensions: Vec<String>,
+
+    /// Never save files with these extensions
+    #[clap(long = "reject-ext", use_value_delimiter = true, parse(from_str = parse_extension), value_name = "EXTENSIONS")]
+    reject_extensions: Vec<String>,
+
+    /// Only download urls matching this pattern, may be repeated
+    #[clap(long, parse(try_from_str = Regex::new), value_name = "REGEX")]
+    include: Vec<Regex>,
+
+    /// Never download urls matching this pattern, may be repeated
+    #[clap(long, parse(try_from_str = Regex::new), value_name = "REGEX")]
+    exclude: Vec<Regex>,
+
+    /// Give up on a url after this many failed retries
+    #[clap(long, default_value_t = 3)]
+    max_retries: usize,
+
+    /// Milliseconds to wait before the first retry, doubled for every further
+    /// one up to a minute
+    #[clap(long, value_name = "MILLIS", default_value_t = 1000)]
+    retry_backoff: u64,
+
+    /// Follow at most this many redirects per request
+    #[clap(long, default_value_t = 10)]
+    max_redirects: usize,
+
+    /// Save pages redirecting to the saved file at the paths of redirected urls
+    #[clap(long)]
+    redirect_stubs: bool,
+
+    /// Load cookies from this Netscape cookies.txt file
+    #[clap(long, value_name = "FILE")]
+    cookies: Option<PathBuf>,
+
+    /// Write the cookies back to the cookies file after the crawl
+    #[clap(long, requires = "cookies")]
+    save_cookies: bool,
+
+    /// User name for HTTP basic auth on the hosts of the targets
+    #[clap(long, requires = "password")]
+    user: Option<String>,
+
+    /// Password for HTTP basic auth on the hosts of the targets
+    #[clap(long, requires = "user")]
+    password: Option<String>,
+
+    /// Log in by posting the login fields to this url before the crawl, the
+    /// session cookies are sent with every request
+    #[clap(long, value_name = "URL")]
+    login_url: Option<Url>,
+
+    /// Field of the login form, may be repeated
+    #[clap(long = "login-field", requires = "login-url", parse(try_from_str = login::parse_field), value_name = "NAME=VALUE")]
+    login_fields: Vec<(String, String)>,
+
+    /// Header sent with every download, can be given multiple times
+    #[clap(long = "header", parse(try_from_str = wmt::parse_header), value_name = "NAME: VALUE")]
+    headers: Vec<(HeaderName, HeaderValue)>,
+
+    /// User agent sent instead of the default `wmt/<version>`
+    #[clap(long)]
+    user_agent: Option<String>,
+
+    /// Shorten queries in file names longer than this many bytes
+    #[clap(long, value_name = "BYTES", default_value_t = long_paths::MAX_NAME_LEN)]
+    max_file_name_len: usize,
+
+    /// Receive at most this many bytes per second, with an optional k, m or g suffix
+    #[clap(long, parse(try_from_str = parse_byte_size), value_name = "BYTES")]
+    limit_rate: Option<u64>,
+
+    /// Seconds to wait for the next part of a response before giving up on it
+    #[clap(long, value_name = "SECS", default_value = "3", parse(try_from_str = parse_seconds))]
+    timeout: Duration,
+
+    /// Seconds to wait for a connection to be established
+    #[clap(long, value_name = "SECS", parse(try_from_str = parse_seconds))]
+    connect_timeout: Option<Duration>,
+
+    /// Send all requests through this HTTP or SOCKS5 proxy instead of the one
+    /// of HTTP_PROXY and HTTPS_PROXY (e.g. socks5://localhost:1080)
+    #[clap(long, parse(try_from_str = parse_proxy), value_name = "URL")]
+    proxy: Option<Url>,
+
+    /// Accept invalid and self-signed TLS certificates. Dangerous: the traffic
+    /// can be read and changed unnoticed
+    #[clap(long)]
+    insecure: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Serve a mirror over HTTP to browse it
+    Serve {
+        /// Output path of the mirror
+        #[clap(parse(from_os_str), default_value = ".")]
+        dir: PathBuf,
+
+        /// Address to listen on
+        #[clap(long, default_value = "127.0.0.1:8000", value_name = "ADDR")]
+        addr: SocketAddr,
+    },
+}
+
+fn parse_host_override(src: &str) -> Result<(String, IpAddr), String> {
+    let (host, ip) = src
+        .split_once(':')
+        .ok_or_else(|| format!("expected `HOST:IP`, got `{src}`"))?;
+    let ip = ip
+        .parse()
+        .map_err(|err| format!("invalid address `{ip}`: {err}"))?;
+
+    Ok((host.to_string(), ip))
+}
+
+fn parse_byte_size(src: &str) -> Result<u64, String> {
+    let (number, factor) = match src.char_indices().last() {
+        Some((index, 'k' | 'K')) => (&src[..index], 1 << 10),
+        Some((index, 'm' | 'M')) => (&src[..index], 1 << 20),
+        Some((index, 'g' | 'G')) => (&src[..index], 1 << 30),
+        _ => (src, 1),
+    };
+
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(factor))
+        .ok_or_else(|| format!("invalid size `{src}`"))
+}
+
+fn parse_seconds(src: &str) -> Result<Duration, String> {
+    match src.parse::<f64>() {
+        Ok(secs) if secs.is_finite() && secs >= 0.0 => Ok(Duration::from_secs_f64(secs)),
+        _ => Err(format!("invalid number of seconds `{src}`")),
+    }
+}
+
+fn parse_proxy(src: &str) -> Result<Url, String> {
+    let url = Url::parse(src).map_err(|err| format!("invalid proxy `{src}`: {err}"))?;
+    Proxy::all(url.clone()).map_err(|err| format!("invalid proxy `{src}`: {err}"))?;
+
+    Ok(url)
+}
+
+impl Args {
+    /// Level to log at instead of showing progress bars, if any
+    fn log_level(&self) -> Option<LevelFilter> {
+        match (self.quiet, self.verbose) {
+            (true, _) => Some(LevelFilter::Error),
+            (false, 0) => None,
+            (false, 1) => Some(LevelFilter::Info),
+            (false, 2) => Some(LevelFilter::Debug),
+            (false, _) => Some(LevelFilter::Trace),
+        }
+    }
+
+    /// Turns on the options implied by `--mirror`
+    ///
+    /// There is no depth limit by default, so one given on the command line or
+    /// in the config is kept.
+    fn apply_mirror(&mut self) {
+        if self.mirror {
+            self.convert_links = true;
+            self.incremental = true;
+        }
+    }
+}
+
+/// The command line `cli` with the options of `config` it does not give put
+/// in front, so they are parsed like flags
+fn with_config(
+    config: &Config,
+    cli: &[OsString],
+    matches: &ArgMatches,
+) -> Result<Vec<OsString>, String> {
+    let command = Args::command();
+    let mut args = cli.iter().take(1).cloned().collect::<Vec<_>>();
+
+    for (name, value) in config.options().map_err(|err| err.to_string())? {
+        let arg = command
+            .get_arguments()
+            .find(|arg| match arg.get_long() {
+                Some(long) => long == name,
+                None => arg.is_positional() && arg.get_id() == name,
+            })
+            .ok_or_else(|| format!("unknown option `{name}`"))?;
+        if matches.occurrences_of(arg.get_id()) > 0 {
+            continue;
+        }
+
+        let flag = format!("--{name}");
+        match value {
+            OptionValue::Switch(on) if !arg.is_takes_value_set() => {
+                if on {
+                    args.push(flag.into());
+                }
+            }
+            OptionValue::Switch(_) => return Err(format!("option `{name}` needs a value")),
+            // counted flags like `verbose`
+            OptionValue::Values(values) if !arg.is_takes_value_set() => {
+                let count = match values.as_slice() {
+                    [count] => count.parse().ok(),
+                    _ => None,
+                }
+                .ok_or_else(|| format!("option `{name}` is a flag, not `{values:?}`"))?;
+                args.extend(iter::repeat(OsString::from(&flag)).take(count));
+            }
+            OptionValue::Values(values) if arg.is_positional() => {
+                args.extend(values.into_iter().map(OsString::from));
+            }
+            OptionValue::Values(values) => {
+                args.extend(
+                    values
+                        .into_iter()
+                        .map(|value| OsString::from(format!("{flag}={value}"))),
+                );
+            }
+        }
+    }
+
+    args.extend(cli.iter().skip(1).cloned());
+    Ok(args)
+}
+
+fn parse_threads(src: &str) -> Result<usize, String> {
+    match src.parse() {
+        Ok(0) => Err(String::from("at least one thread is needed")),
+        Ok(threads) => Ok(threads),
+        Err(err) => Err(format!("invalid thread count `{src}`: {err}")),
+    }
+}
+
+fn parse_extension(src: &str) -> String {
+    src.trim().trim_start_matches('.').to_string()
+}
+
+fn parse_rfc3339(src: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(src)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|err| format!("invalid date `{src}`: {err}"))
 }
 
 fn main() {
-    let args = Args::parse();
+    let cli = std::env::args_os().collect::<Vec<_>>();
+    let matches = Args::command().get_matches_from(&cli);
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    if let Some(Command::Serve { dir, addr }) = &args.command {
+        serve_mirror(dir, *addr);
+        return;
+    }
 
-    if args.targets.is_empty() {
+    if let Some(path) = args.config.clone() {
+        let loaded = Config::load(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|config| with_config(&config, &cli, &matches))
+            .and_then(|cli| Args::try_parse_from(cli).map_err(|err| err.to_string()));
+        match loaded {
+            Ok(loaded) => args = loaded,
+            Err(err) => {
+                println!("{} loading config: {err}", style("Error").red());
+                return;
+            }
+        }
+    }
+    args.apply_mirror();
+
+    let log_level = args.log_level();
+    if let Some(log_level) = log_level {
+        env_logger::Builder::new()
+            .filter_level(log_level.min(LevelFilter::Warn))
+            .filter_module("wmt", log_level)
+            .parse_default_env()
+            .init();
+    }
+
+    // credentials in the targets are only used for basic auth
+    let target_credentials = args
+        .targets
+        .iter_mut()
+        .filter_map(wmt::take_credentials)
+        .last();
+    let basic_auth = args.user.zip(args.password).or(target_credentials);
+
+    if args.targets.is_empty() && args.relayout_from.is_none() {
         println!("{} no targets provided.\n", style("Error").red());
         Args::command().print_help().unwrap();
     }
 
-    let settings = Settings::builder()
+    let mut progress_style = if args.fira_code {
+        progress_style::Config::fira_code()
+    } else {
+        progress_style::Config::default()
+    };
+    if let Some(tick_chars) = args.tick_chars {
+        progress_style.tick_chars = tick_chars;
+    }
+    if let Some(spinner_template) = args.spinner_template {
+        progress_style.spinner_template = spinner_template;
+    }
+    if let Some(bar_template) = args.bar_template {
+        progress_style.bar_template = bar_template;
+    }
+    if let Some(total_template) = args.total_template {
+        progress_style.total_template = total_template;
+    }
+
+    let mut settings = Settings::builder()
         .output_path(args.output)
         .targets(args.targets)
+        .min_free_space(args.min_free_space)
+        .host_overrides(args.host_overrides.into_iter().collect())
+        .normalize_charset(args.normalize_charset)
+        .ramp_up(args.ramp_up.map(Duration::from_millis))
+        .follow_metadata(args.follow_metadata)
+        .allowed_ports(Some(args.allowed_ports).filter(|ports| !ports.is_empty()))
+        .block_private_addresses(args.block_private_addresses)
+        .allowed_private_hosts(args.allowed_private_hosts)
+        .checksums(args.checksums)
+        .on_partial(args.on_partial)
+        .max_css_depth(args.max_css_depth)
+        .normalize_percent_encoding(args.normalize_percent_encoding)
+        .sort_query(args.sort_query)
+        .metrics(args.metrics)
+        .query_layout(args.query_layout)
+        .flat(args.flat)
+        .output_format(args.output_format)
+        .identities(args.identities)
+        .identity_selection(args.identity_selection)
+        .max_errors(args.max_errors)
+        .progress_style(progress_style)
+        .visited_store(args.visited_store)
+        .accept(args.accept)
+        .spill_queue(args.spill_queue)
+        .per_host_byte_budget(args.per_host_byte_budget)
+        .max_per_host(args.max_per_host.map(NonZeroUsize::get))
+        .skip_noindex(args.skip_noindex)
+        .shard_output(args.shard_output)
+        .modified_since(args.modified_since)
+        .incremental(args.incremental)
+        .no_clobber(args.no_clobber)
+        .dedup_content(args.dedup_content)
+        .rate_limit(args.limit_rate)
+        .read_timeout(args.timeout)
+        .connect_timeout(args.connect_timeout)
+        .proxy(args.proxy)
+        .danger_accept_invalid_certs(args.insecure)
+        .follow_offsite_redirects(args.follow_offsite_redirects)
+        .span_subdomains(args.span_subdomains)
+        .span_hosts(args.span_hosts)
+        .generate_rewrite_map(args.rewrite_map)
+        .manifest(args.manifest)
+        .save_error_pages(args.save_error_pages)
+        .error_log(args.log_errors)
+        .dry_run(args.dry_run)
+        .max_file_size(args.max_file_size)
+        .log_rotate_bytes(args.log_rotate_bytes)
+        .compress_logs(args.compress_logs)
+        .convert_links(args.convert_links)
+        .respect_robots(!args.ignore_robots)
+        .use_sitemap(args.sitemap)
+        .request_delay(args.request_delay.map(Duration::from_millis))
+        .random_wait(args.random_wait)
+        .max_depth(args.max_depth)
+        .include(args.include)
+        .accept_extensions(args.accept_extensions)
+        .reject_extensions(args.reject_extensions)
+        .exclude(args.exclude)
+        .max_retries(args.max_retries)
+        .retry_backoff(Duration::from_millis(args.retry_backoff))
+        .max_redirects(args.max_redirects)
+        .redirect_stubs(args.redirect_stubs)
+        .cookies(args.cookies)
+        .save_cookies(args.save_cookies)
+        .basic_auth(basic_auth)
+        .login(args.login_url.map(|url| LoginConfig {
+            url,
+            fields: args.login_fields,
+        }))
+        .headers(args.headers.into_iter().collect())
+        .user_agent(args.user_agent)
+        .max_file_name_len(args.max_file_name_len)
+        .significant_params(Some(args.significant_params).filter(|params| !params.is_empty()))
+        .ignore_query(args.ignore_query)
         .build();
+    settings.targets = settings
+        .targets
+        .iter()
+        .map(|target| settings.canonicalize(target.clone()))
+        .collect();
 
-    run_worker_pool(settings, args.threads);
+    if args.scope_report {
+        print_scope_report(settings);
+        return;
+    }
+
+    if let Some(source) = args.relayout_from {
+        match relayout::relayout(&source, &settings) {
+            Ok(copied) => println!("{} {copied} files", style("Copied").green()),
+            Err(err) => println!("{} copying mirror: {err}", style("Error").red()),
+        }
+        return;
+    }
+
+    let dry_run = settings.dry_run;
+    let stats = run_worker_pool(settings, args.threads, log_level.is_some());
+    if let (Some(stats), false) = (stats, args.quiet) {
+        println!(
+            "{} {} files ({}) in {}, skipped {} and failed {} urls",
+            style(if dry_run { "Found" } else { "Downloaded" }).green(),
+            stats.downloaded,
+            HumanBytes(stats.bytes),
+            HumanDuration(stats.duration),
+            stats.skipped,
+            stats.errors,
+        );
+        if stats.deduplicated > 0 {
+            println!(
+                "{} {} of identical files",
+                style("Linked").green(),
+                HumanBytes(stats.deduplicated),
+            );
+        }
+    }
+}
+
+/// User agent of the settings or the default one
+fn user_agent(settings: &Settings) -> &str {
+    settings.user_agent.as_deref().unwrap_or(APP_USER_AGENT)
 }
 
-fn run_worker_pool(settings: Settings, threads: usize) {
-    let client = Client::builder()
-        .user_agent(APP_USER_AGENT)
+/// Client builder of the settings with the user agent, storing cookies in
+/// `cookie_jar`
+fn client_builder(settings: &Settings, cookie_jar: &Arc<CookieJar>) -> ClientBuilder {
+    settings
+        .client_builder()
+        .user_agent(user_agent(settings))
+        .cookie_provider(cookie_jar.clone())
+}
+
+fn print_scope_report(settings: Settings) {
+    let client = settings
+        .client_builder()
+        .user_agent(user_agent(&settings))
         .build()
         .unwrap();
-    let multi_progress = MultiProgress::new();
-    let priority_queue = PriorityQueue::new();
-    let checked_urls = DashSet::new();
-    let latch = Arc::new(CountdownEvent::new(threads));
-    let downloaded_urls = DashSet::new();
+    let worker = Worker::new(
+        client,
+        PriorityQueue::new(),
+        ProgressBar::hidden(),
+        settings,
+        Arc::new(DashSet::new()),
+        Arc::new(DashSet::new()),
+    );
+
+    match worker.scope_report() {
+        Ok(decisions) => decisions.iter().for_each(|decision| println!("{decision}")),
+        Err(err) => println!("{} creating scope report: {err}", style("Error").red()),
+    }
+}
+
+/// Crawls the targets, returns the summary unless the crawl could not start
+///
+/// Events are logged instead of printed above progress bars if `log_events` is set.
+fn run_worker_pool(settings: Settings, threads: usize, log_events: bool) -> Option<CrawlStats> {
+    let start = Instant::now();
+    let cookie_jar = match &settings.cookies {
+        Some(path) => match CookieJar::load(path) {
+            Ok(cookie_jar) => Arc::new(cookie_jar),
+            Err(err) => {
+                println!("{} loading cookies: {err}", style("Error").red());
+                return None;
+            }
+        },
+        // sessions set by the site or a login need a jar without a cookie file
+        None => Arc::new(CookieJar::default()),
+    };
+    let client = client_builder(&settings, &cookie_jar).build().unwrap();
+    let multi_progress = if log_events {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    };
+    let priority_queue = match &settings.spill_queue {
+        Some(dir) => match PriorityQueue::with_disk_spill(dir, DEFAULT_SPILL_CAPACITY) {
+            Ok(priority_queue) => priority_queue,
+            Err(err) => {
+                println!("{} opening queue spill: {err}", style("Error").red());
+                return None;
+            }
+        },
+        None => PriorityQueue::new(),
+    }
+    .with_deduplication();
+    let (checked_urls, downloaded_urls) = match visited_stores(&settings) {
+        Ok(stores) => stores,
+        Err(err) => {
+            println!("{} opening visited store: {err}", style("Error").red());
+            return None;
+        }
+    };
+    let busy = Arc::new(BusyWorkers::new(threads));
+    let runtime = match RuntimeBuilder::new_multi_thread().enable_all().build() {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            println!("{} starting runtime: {err}", style("Error").red());
+            return None;
+        }
+    };
+
+    if !settings.dry_run {
+        match handle_partial_files(&settings.output_path, settings.on_partial) {
+            Ok(urls) => urls
+                .into_iter()
+                .for_each(|url| priority_queue.push(url, None)),
+            Err(err) => println!("{} handling partial files: {err}", style("Error").red()),
+        }
+    }
 
     for url in &settings.targets {
         priority_queue.push(url.clone(), None);
-        insert_files(&settings.output_path, url, &downloaded_urls);
+        insert_files(&settings, url, &*downloaded_urls);
+    }
+
+    let checksums = settings.checksums.then(|| Arc::new(Checksums::default()));
+    if let Some(login) = &settings.login {
+        if let Err(err) = runtime.block_on(login.log_in(&client)) {
+            println!("{} logging in: {err}", style("Error").red());
+            return None;
+        }
     }
+    let etags = match ETags::load(&settings.output_path) {
+        Ok(etags) => Arc::new(etags),
+        Err(err) => {
+            println!("{} loading etags: {err}", style("Error").red());
+            return None;
+        }
+    };
+    let warc = match settings.output_format {
+        OutputFormat::Warc if !settings.dry_run => {
+            match WarcWriter::create(&settings.output_path.join(WARC_FILE_NAME)) {
+                Ok(warc) => Some(Arc::new(warc)),
+                Err(err) => {
+                    println!("{} opening WARC file: {err}", style("Error").red());
+                    return None;
+                }
+            }
+        }
+        _ => None,
+    };
+    let client_pool = if settings.identities.is_empty() {
+        None
+    } else {
+        // every identity keeps its own session, starting from the cookies
+        // loaded and set by the login
+        match ClientPool::new(&settings.identities, settings.identity_selection, || {
+            client_builder(&settings, &Arc::new(cookie_jar.as_ref().clone()))
+        }) {
+            Ok(client_pool) => Some(Arc::new(client_pool)),
+            Err(err) => {
+                println!("{} building identity clients: {err}", style("Error").red());
+                return None;
+            }
+        }
+    };
+    let state = State::default();
 
-    (0..threads).for_each(|_| {
-        spawn_worker(
+    // pick up where an interrupted crawl stopped
+    if !settings.dry_run {
+        match CrawlSnapshot::load(&settings.output_path) {
+            Ok(Some(snapshot)) => {
+                println!(
+                    "{} crawl with {} pending urls",
+                    style("Resuming").green(),
+                    snapshot.pending.len()
+                );
+                snapshot.restore(&*checked_urls, &*downloaded_urls, &state, &priority_queue);
+            }
+            Ok(None) => {}
+            Err(err) => println!("{} loading crawl state: {err}", style("Error").red()),
+        }
+    }
+
+    let handler_state = state.clone();
+    let handler = ctrlc::set_handler(move || {
+        if handler_state.is_shut_down() {
+            // asked twice, stop without waiting for the current downloads
+            std::process::exit(130);
+        }
+        handler_state.shutdown();
+    });
+    if let Err(err) = handler {
+        println!("{} handling Ctrl-C: {err}", style("Error").red());
+    }
+
+    if let Some(addr) = settings.metrics {
+        if let Err(err) = metrics::serve(addr, state.metrics().clone(), priority_queue.clone()) {
+            println!("{} serving metrics: {err}", style("Error").red());
+        }
+    }
+
+    let workers = build_workers(threads, &settings, &multi_progress, |progress_bar| {
+        let worker = Worker::new(
             client.clone(),
             priority_queue.clone(),
-            &multi_progress,
+            progress_bar,
             settings.clone(),
             checked_urls.clone(),
             downloaded_urls.clone(),
-            latch.clone(),
         )
+        .with_checksums(checksums.clone())
+        .with_state(state.clone())
+        .with_client_pool(client_pool.clone())
+        .with_etags(Some(etags.clone()))
+        .with_warc(warc.clone());
+        if log_events {
+            worker.with_observer(Arc::new(LogObserver))
+        } else {
+            worker
+        }
     });
+    workers
+        .into_iter()
+        .for_each(|worker| spawn_worker(&runtime, worker, busy.clone()));
+
+    let total_progress_bar = multi_progress
+        .add(ProgressBar::new(0))
+        .with_style(progress_style::total(&settings.progress_style))
+        .with_prefix("Total");
+    spawn_total_progress(
+        total_progress_bar,
+        priority_queue.clone(),
+        busy.clone(),
+        state.clone(),
+    );
+    if !settings.dry_run {
+        spawn_state_saver(
+            settings.output_path.clone(),
+            checked_urls.clone(),
+            downloaded_urls.clone(),
+            priority_queue.clone(),
+            busy,
+            state.clone(),
+        );
+    }
 
     multi_progress.join().unwrap();
+    let stats = state.metrics().stats(start.elapsed());
+
+    if state.is_shut_down() {
+        println!(
+            "{} crawl after {} failed and {} completed downloads",
+            style("Aborted").red(),
+            state.metrics().errors(),
+            state.metrics().downloads(),
+        );
+    }
+
+    if let Some(path) = &settings.error_log {
+        if let Err(err) = state.error_log().append(path, settings.rotation()) {
+            println!("{} writing error log: {err}", style("Error").red());
+        }
+    }
+
+    // nothing was saved
+    if settings.dry_run {
+        return Some(stats);
+    }
+
+    if let Err(err) =
+        CrawlSnapshot::capture(&*checked_urls, &*downloaded_urls, &state, &priority_queue)
+            .save(&settings.output_path)
+    {
+        println!("{} saving crawl state: {err}", style("Error").red());
+    }
+
+    if let (Some(path), true) = (&settings.cookies, settings.save_cookies) {
+        if let Err(err) = cookie_jar.save(path) {
+            println!("{} saving cookies: {err}", style("Error").red());
+        }
+    }
+
+    if let Err(err) = etags.save(&settings.output_path) {
+        println!("{} saving etags: {err}", style("Error").red());
+    }
+
+    if let Some(checksums) = checksums {
+        if let Err(err) = checksums.write_manifest(&settings.output_path, settings.rotation()) {
+            println!("{} writing checksums: {err}", style("Error").red());
+        }
+    }
+
+    if !state.hashed_paths().is_empty() {
+        if let Err(err) = state.hashed_paths().write_manifest(&settings.output_path) {
+            println!("{} writing hashed paths: {err}", style("Error").red());
+        }
+    }
+
+    if settings.generate_rewrite_map {
+        if let Err(err) = state.rewrite_map().write(&settings.output_path) {
+            println!("{} writing rewrite map: {err}", style("Error").red());
+        }
+    }
+
+    if let Some(path) = &settings.manifest {
+        if let Err(err) = state.manifest().write(path, settings.rotation()) {
+            println!("{} writing manifest: {err}", style("Error").red());
+        }
+    }
+
+    Some(stats)
+}
+
+/// Serves the mirror in `dir` on `addr` until the process is stopped
+fn serve_mirror(dir: &Path, addr: SocketAddr) {
+    let listener = match TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(err) => {
+            println!("{} listening on {addr}: {err}", style("Error").red());
+            return;
+        }
+    };
+
+    println!(
+        "{} {} on http://{}/",
+        style("Serving").green().bold(),
+        dir.display(),
+        listener.local_addr().unwrap_or(addr)
+    );
+    if let Err(err) = serve::serve(dir, listener) {
+        println!("{} serving the mirror: {err}", style("Error").red());
+    }
 }
 
-fn insert_files(output_path: &Path, url: &Url, urls: &DashSet<Url>) {
-    if let Some(host) = url.host_str() {
-        WalkDir::new(output_path.join(host))
+/// The sets of checked and downloaded urls
+fn visited_stores(
+    settings: &Settings,
+) -> wmt::Result<(Arc<dyn VisitedStore>, Arc<dyn VisitedStore>)> {
+    match &settings.visited_store {
+        Some(path) => {
+            let env = visited::open_environment(path)?;
+            Ok((
+                Arc::new(DiskStore::new(env.clone(), "checked")?),
+                Arc::new(DiskStore::new(env, "downloaded")?),
+            ))
+        }
+        None => Ok((Arc::new(DashSet::new()), Arc::new(DashSet::new()))),
+    }
+}
+
+fn insert_files(settings: &Settings, url: &Url, urls: &dyn VisitedStore) {
+    let output_path = &settings.output_path;
+
+    if let Some(host_path) = settings.host_path(url) {
+        WalkDir::new(output_path.join(&host_path))
             .into_iter()
             .filter_map(|e| e.ok())
             .map(|entry| entry.into_path())
+            // partial downloads are not complete yet
+            .filter(|path| !is_partial_file(path))
             .filter_map(|path| {
                 path.strip_prefix(output_path)
-                    .map(|path| path.strip_prefix(host).ok())
+                    .map(|path| path.strip_prefix(&host_path).ok())
                     .ok()
                     .flatten()
                     .map(|p| p.display().to_string())
             })
             .filter_map(|path| url.join(&path).ok())
+            // the file may be named with the extension of its type appended
+            .flat_map(|url| {
+                let stripped = strip_type_extension(&url);
+                iter::once(url).chain(stripped)
+            })
+            .map(|url| settings.canonicalize(url))
             .for_each(|url| {
                 urls.insert(url);
             });
     }
 }
 
-fn spawn_worker(
-    client: Client,
-    priority_queue: PriorityQueue<Url>,
+/// One worker per thread built by `new_worker`, each with its own spinner in
+/// `multi_progress` and starting after its share of the ramp up
+fn build_workers<F>(
+    threads: usize,
+    settings: &Settings,
     multi_progress: &MultiProgress,
-    settings: Settings,
-    checked_urls: DashSet<Url>,
-    downloaded_urls: DashSet<Url>,
-    latch: Arc<CountdownEvent>,
+    new_worker: F,
+) -> Vec<Worker>
+where
+    F: Fn(ProgressBar) -> Worker,
+{
+    (0..threads)
+        .map(|index| {
+            let progress_bar = multi_progress
+                .add(ProgressBar::new_spinner())
+                .with_style(progress_style::spinner(&settings.progress_style))
+                .with_message("Starting");
+
+            new_worker(progress_bar).with_start_delay(settings.start_delay(index, threads))
+        })
+        .collect()
+}
+
+fn spawn_worker(runtime: &Runtime, worker: Worker, busy: Arc<BusyWorkers>) {
+    runtime.spawn(async move { worker.crawl(&busy).await.unwrap() });
+}
+
+/// Shows the completed and seen urls of the crawl until the workers are done
+fn spawn_total_progress(
+    progress_bar: ProgressBar,
+    priority_queue: PriorityQueue<Url>,
+    busy: Arc<BusyWorkers>,
+    state: State,
 ) {
-    let progress_bar = multi_progress
-        .add(ProgressBar::new_spinner())
-        .with_style(progress_style::spinner())
-        .with_message("Starting");
+    thread::spawn(move || loop {
+        let metrics = state.metrics();
+        let remaining = priority_queue.len() as u64;
+        let completed = metrics.completed();
 
-    let worker = Worker::new(
-        client,
-        priority_queue,
-        progress_bar,
-        settings,
-        checked_urls,
-        downloaded_urls,
-    );
+        progress_bar.set_length(completed + metrics.in_flight() + remaining);
+        progress_bar.set_position(completed);
+        progress_bar.set_message(format!("{remaining} remaining"));
 
-    thread::spawn(|| worker.run(latch).unwrap());
+        if state.is_shut_down() || busy.count() == 0 && priority_queue.is_empty() {
+            progress_bar.finish();
+            break;
+        }
+        thread::sleep(Duration::from_millis(200));
+    });
+}
+
+/// Writes the state file every [`STATE_SAVE_INTERVAL`] until the workers are done
+fn spawn_state_saver(
+    output_path: PathBuf,
+    checked_urls: Arc<dyn VisitedStore>,
+    downloaded_urls: Arc<dyn VisitedStore>,
+    priority_queue: PriorityQueue<Url>,
+    busy: Arc<BusyWorkers>,
+    state: State,
+) {
+    thread::spawn(move || {
+        let mut last_save = Instant::now();
+
+        loop {
+            // the final state is saved once the workers are done
+            if state.is_shut_down() || busy.count() == 0 && priority_queue.is_empty() {
+                break;
+            }
+
+            if last_save.elapsed() >= STATE_SAVE_INTERVAL {
+                let snapshot = CrawlSnapshot::capture(
+                    &*checked_urls,
+                    &*downloaded_urls,
+                    &state,
+                    &priority_queue,
+                );
+                if let Err(err) = snapshot.save(&output_path) {
+                    warn!("saving crawl state: {err}");
+                }
+                last_save = Instant::now();
+            }
+            thread::sleep(Duration::from_millis(200));
+        }
+    });
 }
 
 #[cfg(test)]
@@ -137,3 +1203,207 @@ fn verify_app() {
     use clap::CommandFactory;
     Args::command().debug_assert()
 }
+
+#[cfg(test)]
+#[test]
+fn resolve_flag() {
+    let args = Args::parse_from([
+        "wmt",
+        "--resolve",
+        "www.example.com:10.0.0.1",
+        "--resolve",
+        "staging.example.com:::1",
+    ]);
+
+    assert_eq!(
+        vec![
+            (String::from("www.example.com"), IpAddr::from([10, 0, 0, 1])),
+            (
+                String::from("staging.example.com"),
+                IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1])
+            ),
+        ],
+        args.host_overrides
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn threads_flag() {
+    let args = Args::parse_from(["wmt", "--threads", "8"]);
+    let settings = Settings::builder().output_path(".").targets(vec![]).build();
+    let multi_progress = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+
+    let workers = build_workers(args.threads, &settings, &multi_progress, |progress_bar| {
+        Worker::new(
+            reqwest::Client::new(),
+            PriorityQueue::new(),
+            progress_bar,
+            settings.clone(),
+            Arc::new(DashSet::new()),
+            Arc::new(DashSet::new()),
+        )
+    });
+
+    assert_eq!(8, workers.len());
+    assert!(Args::try_parse_from(["wmt", "--threads", "0"]).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn extension_flags() {
+    let args = Args::parse_from(["wmt", "--accept-ext", "jpg,.PNG", "--reject-ext", "pdf"]);
+
+    assert_eq!(vec!["jpg", "PNG"], args.accept_extensions);
+    assert_eq!(vec!["pdf"], args.reject_extensions);
+}
+
+#[cfg(test)]
+#[test]
+fn limit_rate_flag() {
+    assert_eq!(
+        Some(500 * 1024),
+        Args::parse_from(["wmt", "--limit-rate", "500k"]).limit_rate
+    );
+    assert_eq!(
+        Some(2 << 20),
+        Args::parse_from(["wmt", "--limit-rate", "2M"]).limit_rate
+    );
+    assert!(Args::try_parse_from(["wmt", "--limit-rate", "fast"]).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn config_file() {
+    let cli = ["wmt", "--threads", "8", "--depth", "1"].map(OsString::from);
+    let matches = Args::command().get_matches_from(&cli);
+    let config = toml::from_str(
+        r#"
+            targets = ["https://example.com/"]
+            threads = 2
+            delay = 250
+            depth = 3
+            exclude = ["\\.pdf$"]
+            verbose = 2
+            span-hosts = true
+            sort-query = false
+            limit-rate = "2M"
+            user = "me"
+            password = "secret"
+            header = ["X-Team: docs", "X-Run: nightly"]
+        "#,
+    )
+    .unwrap();
+
+    let args = Args::parse_from(with_config(&config, &cli, &matches).unwrap());
+
+    assert_eq!(
+        vec![Url::parse("https://example.com/").unwrap()],
+        args.targets
+    );
+    assert_eq!(8, args.threads);
+    assert_eq!(Some(250), args.request_delay);
+    assert_eq!(Some(1), args.max_depth);
+    assert!(args.exclude[0].is_match("https://example.com/manual.pdf"));
+    assert_eq!(Some(LevelFilter::Debug), args.log_level());
+    assert!(args.span_hosts);
+    assert!(!args.sort_query);
+    assert_eq!(Some(2 << 20), args.limit_rate);
+    assert_eq!(Some("me"), args.user.as_deref());
+    assert_eq!(2, args.headers.len());
+
+    for config in ["thread = 4", "span-hosts = \"yes\"", "delay = true"] {
+        let config = toml::from_str(config).unwrap();
+        assert!(with_config(&config, &cli, &matches)
+            .and_then(|cli| Args::try_parse_from(cli).map_err(|err| err.to_string()))
+            .is_err());
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn verbosity_flags() {
+    assert_eq!(None, Args::parse_from(["wmt"]).log_level());
+    assert_eq!(
+        Some(LevelFilter::Error),
+        Args::parse_from(["wmt", "--quiet"]).log_level()
+    );
+    assert_eq!(
+        Some(LevelFilter::Info),
+        Args::parse_from(["wmt", "-v"]).log_level()
+    );
+    assert_eq!(
+        Some(LevelFilter::Debug),
+        Args::parse_from(["wmt", "-vv"]).log_level()
+    );
+    assert!(Args::try_parse_from(["wmt", "-q", "-v"]).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn mirror_flag() {
+    let mut args = Args::parse_from(["wmt", "-m", "--depth", "2"]);
+    args.apply_mirror();
+
+    assert!(args.convert_links);
+    assert!(args.incremental);
+    assert_eq!(Some(2), args.max_depth);
+
+    let mut args = Args::parse_from(["wmt"]);
+    args.apply_mirror();
+
+    assert!(!args.convert_links);
+    assert!(!args.incremental);
+}
+
+#[cfg(test)]
+#[test]
+fn serve_subcommand() {
+    let args = Args::parse_from(["wmt", "serve", "mirror", "--addr", "0.0.0.0:8080"]);
+
+    match args.command {
+        Some(Command::Serve { dir, addr }) => {
+            assert_eq!(PathBuf::from("mirror"), dir);
+            assert_eq!("0.0.0.0:8080".parse::<SocketAddr>().unwrap(), addr);
+        }
+        None => panic!("expected the serve subcommand"),
+    }
+    assert!(Args::parse_from(["wmt", "https://example.com/"])
+        .command
+        .is_none());
+}
+
+#[cfg(test)]
+#[test]
+fn login_flags() {
+    let args = Args::parse_from([
+        "wmt",
+        "--login-url",
+        "https://example.com/login",
+        "--login-field",
+        "user=me",
+        "--login-field",
+        "password=secret",
+    ]);
+
+    assert_eq!(
+        Some(Url::parse("https://example.com/login").unwrap()),
+        args.login_url
+    );
+    assert_eq!(
+        vec![
+            (String::from("user"), String::from("me")),
+            (String::from("password"), String::from("secret")),
+        ],
+        args.login_fields
+    );
+    assert!(Args::try_parse_from(["wmt", "--login-field", "user=me"]).is_err());
+    assert!(Args::try_parse_from([
+        "wmt",
+        "--login-url",
+        "https://example.com/",
+        "--login-field",
+        "user"
+    ])
+    .is_err());
+}
@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+
+use parking_lot::Mutex;
+use reqwest::Url;
+use serde_json::{json, Value};
+
+use crate::{rotate::Rotation, Error, Result};
+
+/// A saved file of the mirror
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub url: Url,
+    /// Path of the file relative to the output directory
+    pub local_path: PathBuf,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub bytes: u64,
+}
+
+impl ManifestEntry {
+    fn to_json(&self) -> Value {
+        json!({
+            "url": self.url.as_str(),
+            "local_path": self.local_path.to_string_lossy(),
+            "status": self.status,
+            "content_type": self.content_type,
+            "bytes": self.bytes,
+        })
+    }
+}
+
+/// Every file saved during a crawl, written as a JSON array
+#[derive(Debug, Default)]
+pub struct Manifest {
+    entries: Mutex<Vec<ManifestEntry>>,
+}
+
+impl Manifest {
+    pub fn insert(&self, entry: ManifestEntry) {
+        self.entries.lock().push(entry);
+    }
+
+    /// Points the entries of the file moved from `from` to `to`
+    pub fn rename(&self, from: &Path, to: &Path) {
+        self.entries
+            .lock()
+            .iter_mut()
+            .filter(|entry| entry.local_path == from)
+            .for_each(|entry| entry.local_path = to.to_path_buf());
+    }
+
+    /// The entries sorted by url
+    pub fn entries(&self) -> Vec<ManifestEntry> {
+        let mut entries = self.entries.lock().clone();
+        entries.sort_by(|a, b| a.url.cmp(&b.url));
+        entries
+    }
+
+    /// Writes the entries to the file at `path`, compressed if `rotation`
+    /// says so
+    ///
+    /// The array is one JSON document, so it is never split into more files.
+    pub fn write(&self, path: &Path, rotation: Rotation) -> Result<()> {
+        let entries = self
+            .entries()
+            .iter()
+            .map(ManifestEntry::to_json)
+            .collect::<Vec<_>>();
+
+        let rotation = Rotation {
+            max_bytes: None,
+            ..rotation
+        };
+        let mut writer = rotation.create(path).map_err(Error::CreateFile)?;
+
+        serde_json::to_writer_pretty(&mut writer, &entries)
+            .map_err(|err| Error::WriteFile(err.into()))?;
+        writer.finish().map_err(Error::WriteFile)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn json() {
+        let output = tempfile::tempdir().unwrap();
+        let path = output.path().join("manifest.json");
+        let manifest = Manifest::default();
+
+        manifest.insert(ManifestEntry {
+            url: Url::parse("https://example.com/logo").unwrap(),
+            local_path: PathBuf::from("example.com/logo.png"),
+            status: 200,
+            content_type: Some(String::from("image/png")),
+            bytes: 42,
+        });
+        manifest.insert(ManifestEntry {
+            url: Url::parse("https://example.com/").unwrap(),
+            local_path: PathBuf::from("example.com/index.html"),
+            status: 200,
+            content_type: None,
+            bytes: 7,
+        });
+        manifest.write(&path, Rotation::default()).unwrap();
+
+        let written: Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            json!([
+                {
+                    "url": "https://example.com/",
+                    "local_path": "example.com/index.html",
+                    "status": 200,
+                    "content_type": null,
+                    "bytes": 7,
+                },
+                {
+                    "url": "https://example.com/logo",
+                    "local_path": "example.com/logo.png",
+                    "status": 200,
+                    "content_type": "image/png",
+                    "bytes": 42,
+                },
+            ]),
+            written
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use serde_json::Value;
+use tl::VDom;
+
+/// Open Graph properties that reference a resource
+const OPEN_GRAPH_PROPERTIES: &[&str] = &[
+    "og:url",
+    "og:image",
+    "og:image:url",
+    "og:image:secure_url",
+    "og:video",
+    "og:video:url",
+    "og:video:secure_url",
+    "og:audio",
+    "og:audio:url",
+    "og:audio:secure_url",
+];
+
+/// JSON-LD keys whose values are URLs
+const JSON_LD_KEYS: &[&str] = &["@id", "url", "image", "logo", "contentUrl", "thumbnailUrl"];
+
+/// Collects the `content` of `<meta property="og:...">` tags that reference a
+/// resource
+pub fn open_graph_links(dom: &VDom) -> Vec<String> {
+    dom.query_selector(r#"meta[property^="og:"][content]"#)
+        .unwrap()
+        .filter_map(|handle| handle.get(dom.parser()))
+        .filter_map(|node| node.as_tag())
+        .filter(|tag| {
+            tag.attributes()
+                .get("property")
+                .flatten()
+                .map(|property| property.as_utf8_str())
+                .map_or(false, |property| {
+                    OPEN_GRAPH_PROPERTIES.contains(&property.to_ascii_lowercase().as_str())
+                })
+        })
+        .filter_map(|tag| tag.attributes().get("content").flatten())
+        .map(|bytes| bytes.as_utf8_str().into_owned())
+        .collect()
+}
+
+/// Indexing directives of a page
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Robots {
+    pub noindex: bool,
+    pub nofollow: bool,
+}
+
+impl Robots {
+    /// Parses a comma separated list of directives like `noindex, nofollow`.
+    ///
+    /// Directives for a specific user agent (`googlebot: noindex`) are ignored.
+    pub fn parse(directives: &str) -> Self {
+        directives
+            .split(',')
+            .map(|directive| directive.trim().to_ascii_lowercase())
+            .fold(Self::default(), |robots, directive| {
+                match directive.as_str() {
+                    "noindex" => Self {
+                        noindex: true,
+                        ..robots
+                    },
+                    "nofollow" => Self {
+                        nofollow: true,
+                        ..robots
+                    },
+                    "none" => Self {
+                        noindex: true,
+                        nofollow: true,
+                    },
+                    _ => robots,
+                }
+            })
+    }
+
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            noindex: self.noindex || other.noindex,
+            nofollow: self.nofollow || other.nofollow,
+        }
+    }
+}
+
+/// Reads the directives of `<meta name="robots">` tags
+pub fn meta_robots(dom: &VDom) -> Robots {
+    dom.query_selector("meta[name][content]")
+        .unwrap()
+        .filter_map(|handle| handle.get(dom.parser()))
+        .filter_map(|node| node.as_tag())
+        .filter(|tag| {
+            tag.attributes()
+                .get("name")
+                .flatten()
+                .map_or(false, |name| {
+                    name.as_utf8_str().eq_ignore_ascii_case("robots")
+                })
+        })
+        .filter_map(|tag| tag.attributes().get("content").flatten())
+        .map(|content| Robots::parse(&content.as_utf8_str()))
+        .fold(Robots::default(), Robots::merge)
+}
+
+/// Target of the first `<meta http-equiv="refresh">` tag redirecting somewhere
+pub fn meta_refresh(dom: &VDom) -> Option<String> {
+    // selectors match tag names case-sensitively
+    dom.nodes()
+        .iter()
+        .filter_map(|node| node.as_tag())
+        .filter(|tag| tag.name().as_utf8_str().eq_ignore_ascii_case("meta"))
+        .filter_map(|tag| {
+            // and `get` attribute names
+            let attribute = |name: &str| {
+                tag.attributes()
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                    .and_then(|(_, value)| value)
+                    .map(|value| value.into_owned())
+            };
+
+            if !attribute("http-equiv")?.eq_ignore_ascii_case("refresh") {
+                return None;
+            }
+            refresh_url(&attribute("content")?)
+        })
+        .next()
+}
+
+/// Parses the url of a refresh like `0; url='/landing'`
+fn refresh_url(content: &str) -> Option<String> {
+    // the delay
+    let rest = content
+        .trim_start()
+        .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.')
+        .trim_start();
+    let rest = rest.strip_prefix(|c| c == ';' || c == ',')?.trim_start();
+
+    let rest = match rest.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url") => {
+            match rest[3..].trim_start().strip_prefix('=') {
+                Some(url) => url.trim_start(),
+                None => rest,
+            }
+        }
+        _ => rest,
+    };
+
+    let url = match rest.chars().next() {
+        Some(quote @ ('"' | '\'')) => rest[1..].split(quote).next().unwrap_or_default(),
+        _ => rest,
+    }
+    .trim();
+
+    Some(url.to_string()).filter(|url| !url.is_empty())
+}
+
+/// Collects URL fields from `<script type="application/ld+json">` blocks
+pub fn json_ld_links(dom: &VDom) -> Vec<String> {
+    let mut links = Vec::new();
+
+    dom.query_selector(r#"script[type="application/ld+json"]"#)
+        .unwrap()
+        .filter_map(|handle| handle.get(dom.parser()))
+        .filter_map(|node| node.as_tag())
+        .filter_map(|tag| serde_json::from_str(&tag.inner_text(dom.parser())).ok())
+        .for_each(|value: Value| collect_json_ld_links(&value, &mut links));
+
+    links
+}
+
+fn collect_json_ld_links(value: &Value, links: &mut Vec<String>) {
+    match value {
+        Value::Object(object) => object.iter().for_each(|(key, value)| {
+            match value {
+                Value::String(link) if JSON_LD_KEYS.contains(&key.as_str()) => {
+                    links.push(link.clone())
+                }
+                Value::Array(values) if JSON_LD_KEYS.contains(&key.as_str()) => values
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .for_each(|link| links.push(link.to_string())),
+                _ => {}
+            }
+            collect_json_ld_links(value, links)
+        }),
+        Value::Array(values) => values
+            .iter()
+            .for_each(|value| collect_json_ld_links(value, links)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn refresh() {
+        let refresh = |document: &str| {
+            meta_refresh(&tl::parse(document, tl::ParserOptions::default()).unwrap())
+        };
+
+        assert_eq!(
+            Some(String::from("/landing")),
+            refresh(r#"<meta http-equiv="refresh" content="0; url=/landing">"#)
+        );
+        assert_eq!(
+            Some(String::from("https://example.com/consent?next=/")),
+            refresh(
+                r#"<META HTTP-EQUIV="Refresh" CONTENT="5;URL='https://example.com/consent?next=/'">"#
+            )
+        );
+        assert_eq!(
+            Some(String::from("next.html")),
+            refresh(r#"<meta http-equiv="refresh" content="1.5, next.html">"#)
+        );
+        assert_eq!(None, refresh(r#"<meta http-equiv="refresh" content="30">"#));
+        assert_eq!(
+            None,
+            refresh(r#"<meta name="refresh" content="0; url=/landing">"#)
+        );
+    }
+
+    #[test]
+    fn open_graph() {
+        let document = r#"<html><head>
+            <meta property="og:title" content="Some Title">
+            <meta property="og:image" content="https://example.com/cover.png">
+            <meta property="og:url" content="/article">
+        </head></html>"#;
+        let dom = tl::parse(document, tl::ParserOptions::default()).unwrap();
+
+        assert_eq!(
+            vec!["https://example.com/cover.png", "/article"],
+            open_graph_links(&dom)
+        );
+    }
+
+    #[test]
+    fn json_ld() {
+        let document = r#"<html><head>
+            <script type="application/ld+json">
+            {
+                "@context": "https://schema.org",
+                "@type": "Article",
+                "@id": "https://example.com/article",
+                "image": ["/cover.jpg", { "@type": "ImageObject", "contentUrl": "/wide.jpg" }],
+                "publisher": {
+                    "@type": "Organization",
+                    "url": "https://example.com/",
+                    "logo": "/logo.png"
+                },
+                "name": "https://not-a-link-field.example.com"
+            }
+            </script>
+        </head></html>"#;
+        let dom = tl::parse(document, tl::ParserOptions::default()).unwrap();
+
+        let mut links = json_ld_links(&dom);
+        links.sort();
+        assert_eq!(
+            vec![
+                "/cover.jpg",
+                "/logo.png",
+                "/wide.jpg",
+                "https://example.com/",
+                "https://example.com/article",
+            ],
+            links
+        );
+    }
+
+    #[test]
+    fn robots() {
+        let document = r#"<html><head>
+            <meta name="description" content="noindex">
+            <meta name="ROBOTS" content="NoIndex, follow">
+        </head></html>"#;
+        let dom = tl::parse(document, tl::ParserOptions::default()).unwrap();
+
+        assert_eq!(
+            Robots {
+                noindex: true,
+                nofollow: false
+            },
+            meta_robots(&dom)
+        );
+        assert_eq!(
+            Robots {
+                noindex: true,
+                nofollow: true
+            },
+            Robots::parse("none")
+        );
+        assert_eq!(Robots::default(), Robots::parse("googlebot: noindex"));
+    }
+
+    #[test]
+    fn json_ld_invalid() {
+        let document = r#"<script type="application/ld+json">{ not json</script>"#;
+        let dom = tl::parse(document, tl::ParserOptions::default()).unwrap();
+
+        assert!(json_ld_links(&dom).is_empty());
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use reqwest::Url;
+
+use crate::priority_queue::PriorityQueue;
+
+/// Counters describing the progress of a crawl
+#[derive(Debug, Default)]
+pub struct Metrics {
+    requests: AtomicU64,
+    bytes: AtomicU64,
+    downloads: AtomicU64,
+    errors: AtomicU64,
+    in_flight: AtomicU64,
+    skipped: AtomicU64,
+    deduplicated: AtomicU64,
+}
+
+/// Summary of a finished crawl
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CrawlStats {
+    /// Requests which succeeded
+    pub downloaded: u64,
+    /// Urls which were never requested, like ones disallowed by robots.txt
+    pub skipped: u64,
+    /// Requests which failed
+    pub errors: u64,
+    /// Bytes saved to disk
+    pub bytes: u64,
+    /// Bytes of saved files replaced with links to identical ones
+    pub deduplicated: u64,
+    pub duration: Duration,
+}
+
+impl Metrics {
+    pub fn request_started(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn request_finished(&self, success: bool) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        let counter = if success {
+            &self.downloads
+        } else {
+            &self.errors
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn add_deduplicated(&self, bytes: u64) {
+        self.deduplicated.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn url_skipped(&self) {
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn downloads(&self) -> u64 {
+        self.downloads.load(Ordering::Relaxed)
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// Requests which finished, successfully or not
+    pub fn completed(&self) -> u64 {
+        self.downloads() + self.errors()
+    }
+
+    pub fn in_flight(&self) -> u64 {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Summary of the crawl so far, which has been running for `duration`
+    pub fn stats(&self, duration: Duration) -> CrawlStats {
+        CrawlStats {
+            downloaded: self.downloads(),
+            skipped: self.skipped.load(Ordering::Relaxed),
+            errors: self.errors(),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            deduplicated: self.deduplicated.load(Ordering::Relaxed),
+            duration,
+        }
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format
+    pub fn render(&self, queue_depth: usize) -> String {
+        let counters = [
+            ("requests_total", "counter", "Requests sent", &self.requests),
+            ("bytes_total", "counter", "Bytes saved to disk", &self.bytes),
+            (
+                "downloads_total",
+                "counter",
+                "Completed downloads",
+                &self.downloads,
+            ),
+            ("errors_total", "counter", "Failed downloads", &self.errors),
+            ("skipped_total", "counter", "Skipped urls", &self.skipped),
+            (
+                "deduplicated_bytes_total",
+                "counter",
+                "Bytes of files linked to identical ones",
+                &self.deduplicated,
+            ),
+            (
+                "in_flight",
+                "gauge",
+                "Downloads in progress",
+                &self.in_flight,
+            ),
+        ];
+
+        let mut rendered = String::new();
+        for (name, kind, help, value) in counters {
+            let value = value.load(Ordering::Relaxed);
+            write_metric(&mut rendered, name, kind, help, value);
+        }
+        write_metric(
+            &mut rendered,
+            "queue_depth",
+            "gauge",
+            "Urls waiting to be downloaded",
+            queue_depth as u64,
+        );
+
+        rendered
+    }
+}
+
+fn write_metric(rendered: &mut String, name: &str, kind: &str, help: &str, value: u64) {
+    // writing to a string never fails
+    let _ = write!(
+        rendered,
+        "# HELP wmt_{name} {help}\n# TYPE wmt_{name} {kind}\nwmt_{name} {value}\n"
+    );
+}
+
+/// Serves the rendered `metrics` to every request on `addr` in the background
+pub fn serve(
+    addr: SocketAddr,
+    metrics: Arc<Metrics>,
+    priority_queue: PriorityQueue<Url>,
+) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let local_addr = listener.local_addr()?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().filter_map(|stream| stream.ok()) {
+            // a broken scrape must not stop the crawl
+            let _ = respond(stream, &metrics.render(priority_queue.len()));
+        }
+    });
+
+    Ok(local_addr)
+}
+
+fn respond(mut stream: TcpStream, body: &str) -> io::Result<()> {
+    // skip the request, every path serves the metrics
+    let mut reader = BufReader::new(&mut stream);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line != "\r\n" {
+        line.clear();
+    }
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn render() {
+        let metrics = Metrics::default();
+        metrics.request_started();
+        metrics.add_bytes(42);
+        metrics.request_finished(true);
+        metrics.request_started();
+
+        let rendered = metrics.render(3);
+
+        for line in [
+            "wmt_requests_total 2",
+            "wmt_bytes_total 42",
+            "wmt_downloads_total 1",
+            "wmt_errors_total 0",
+            "wmt_in_flight 1",
+            "wmt_queue_depth 3",
+            "# TYPE wmt_in_flight gauge",
+        ] {
+            assert!(rendered.lines().any(|l| l == line), "missing `{line}`");
+        }
+    }
+
+    #[test]
+    fn completed() {
+        let metrics = Metrics::default();
+        metrics.request_started();
+        metrics.request_finished(true);
+        metrics.request_started();
+        metrics.request_finished(false);
+        metrics.request_started();
+
+        assert_eq!(2, metrics.completed());
+        assert_eq!(1, metrics.in_flight());
+    }
+
+    #[test]
+    fn stats() {
+        let metrics = Metrics::default();
+        metrics.request_started();
+        metrics.add_bytes(42);
+        metrics.request_finished(true);
+        metrics.request_started();
+        metrics.request_finished(false);
+        metrics.url_skipped();
+        metrics.add_deduplicated(7);
+
+        assert_eq!(
+            CrawlStats {
+                downloaded: 1,
+                skipped: 1,
+                errors: 1,
+                bytes: 42,
+                deduplicated: 7,
+                duration: Duration::from_secs(3),
+            },
+            metrics.stats(Duration::from_secs(3))
+        );
+    }
+
+    #[test]
+    fn endpoint() {
+        let metrics = Arc::new(Metrics::default());
+        metrics.request_started();
+
+        let addr = serve(
+            "127.0.0.1:0".parse().unwrap(),
+            metrics,
+            PriorityQueue::new(),
+        )
+        .unwrap();
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\nwmt_in_flight 1\n"));
+    }
+}
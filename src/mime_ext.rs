@@ -0,0 +1,107 @@
+use itertools::Itertools;
+use reqwest::Url;
+
+/// File extensions of common MIME types
+const EXTENSIONS: [(&str, &str); 22] = [
+    ("application/javascript", "js"),
+    ("application/json", "json"),
+    ("application/pdf", "pdf"),
+    ("application/xml", "xml"),
+    ("application/zip", "zip"),
+    ("audio/mpeg", "mp3"),
+    ("font/woff", "woff"),
+    ("font/woff2", "woff2"),
+    ("image/avif", "avif"),
+    ("image/gif", "gif"),
+    ("image/jpeg", "jpg"),
+    ("image/png", "png"),
+    ("image/svg+xml", "svg"),
+    ("image/vnd.microsoft.icon", "ico"),
+    ("image/webp", "webp"),
+    ("image/x-icon", "ico"),
+    ("text/css", "css"),
+    ("text/csv", "csv"),
+    ("text/javascript", "js"),
+    ("text/plain", "txt"),
+    ("text/xml", "xml"),
+    ("video/mp4", "mp4"),
+];
+
+/// Extension for files of the MIME type `essence`
+///
+/// HTML is left out on purpose, pages keep the names links to them are
+/// converted to.
+pub fn extension(essence: &str) -> Option<&'static str> {
+    EXTENSIONS
+        .iter()
+        .find(|(mime, _)| mime.eq_ignore_ascii_case(essence))
+        .map(|(_, extension)| *extension)
+}
+
+/// Extensions `extension` may return
+pub fn extensions() -> impl Iterator<Item = &'static str> {
+    EXTENSIONS.iter().map(|(_, extension)| *extension).unique()
+}
+
+/// MIME type of files with `extension`, pages included
+pub fn mime_type(extension: &str) -> Option<&'static str> {
+    if ["html", "htm"]
+        .iter()
+        .any(|page| extension.eq_ignore_ascii_case(page))
+    {
+        return Some("text/html");
+    }
+
+    EXTENSIONS
+        .iter()
+        .find(|(_, known)| known.eq_ignore_ascii_case(extension))
+        .map(|(mime, _)| *mime)
+}
+
+/// Whether the last segment of the path of `url` has an extension
+pub fn has_extension(url: &Url) -> bool {
+    url_extension(url).is_some()
+}
+
+/// Extension of the last segment of the path of `url`
+pub fn url_extension(url: &Url) -> Option<&str> {
+    let name = url.path_segments()?.next_back()?;
+
+    name.rsplit_once('.')
+        .filter(|(stem, extension)| !stem.is_empty() && !extension.is_empty())
+        .map(|(_, extension)| extension)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn extensions() {
+        assert_eq!(Some("png"), extension("image/png"));
+        assert_eq!(Some("jpg"), extension("Image/JPEG"));
+        assert_eq!(Some("css"), extension("text/css"));
+        assert_eq!(None, extension("text/html"));
+        assert_eq!(None, extension("application/octet-stream"));
+    }
+
+    #[test]
+    fn mime_types() {
+        assert_eq!(Some("image/png"), mime_type("png"));
+        assert_eq!(Some("text/html"), mime_type("HTML"));
+        assert_eq!(Some("application/javascript"), mime_type("js"));
+        assert_eq!(None, mime_type("tar"));
+    }
+
+    #[test]
+    fn url_extensions() {
+        let has = |url| has_extension(&Url::parse(url).unwrap());
+
+        assert!(has("https://example.com/logo.png"));
+        assert!(has("https://example.com/index.html?page=2"));
+        assert!(!has("https://example.com/avatar?id=42.jpg"));
+        assert!(!has("https://example.com/.well-known/.hidden"));
+        assert!(!has("https://example.com/"));
+        assert!(!has("https://example.com/v1.2/avatar"));
+    }
+}
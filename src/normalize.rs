@@ -0,0 +1,149 @@
+use reqwest::Url;
+
+/// Normalizes the percent-encoding in the path and query of `url`.
+///
+/// Escaped unreserved characters are decoded and the hex digits of all other
+/// escapes are uppercased, so equivalent urls compare equal.
+pub fn normalize_url(url: &Url) -> Url {
+    let mut normalized = url.clone();
+
+    normalized.set_path(&normalize_percent_encoding(url.path()));
+    if let Some(query) = url.query() {
+        normalized.set_query(Some(&normalize_percent_encoding(query)));
+    }
+
+    normalized
+}
+
+/// Drops all query parameters of `url` not named in `significant`.
+///
+/// Names ending in `*` match every parameter starting with the rest of the name.
+pub fn retain_params(url: &Url, significant: &[String]) -> Url {
+    let mut retained = url.clone();
+
+    let params = url
+        .query_pairs()
+        .filter(|(name, _)| {
+            significant
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => name.starts_with(prefix),
+                    None => name == pattern,
+                })
+        })
+        .collect::<Vec<_>>();
+
+    if params.is_empty() {
+        retained.set_query(None);
+    } else {
+        retained.query_pairs_mut().clear().extend_pairs(params);
+    }
+
+    retained
+}
+
+/// Sorts the query parameters of `url` by name.
+///
+/// Parameters with the same name keep their order, as it may matter to the server.
+pub fn sort_query(url: &Url) -> Url {
+    let mut sorted = url.clone();
+
+    let mut params = url.query_pairs().collect::<Vec<_>>();
+    if params.is_empty() {
+        return sorted;
+    }
+    params.sort_by(|(a, _), (b, _)| a.cmp(b));
+    sorted.query_pairs_mut().clear().extend_pairs(params);
+
+    sorted
+}
+
+/// Normalizes the percent-encoded triplets in `src` as described in RFC 3986
+pub fn normalize_percent_encoding(src: &str) -> String {
+    let bytes = src.as_bytes();
+    let mut normalized = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) if is_unreserved(byte) => normalized.push(byte),
+            Some(byte) => normalized.extend(format!("%{byte:02X}").bytes()),
+            None => {
+                normalized.push(bytes[index]);
+                index += 1;
+                continue;
+            }
+        }
+        index += 3;
+    }
+
+    // only ascii bytes were replaced with other ascii bytes
+    String::from_utf8(normalized).unwrap()
+}
+
+/// Characters which never need to be escaped
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unreserved_characters() {
+        assert_eq!(
+            normalize_url(&Url::parse("https://example.com/%7Euser/a%2db").unwrap()),
+            normalize_url(&Url::parse("https://example.com/~user/a-b").unwrap())
+        );
+    }
+
+    #[test]
+    fn reserved_characters() {
+        assert_eq!("a%2Fb%3F%20c", normalize_percent_encoding("a%2fb%3f%20c"));
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        assert_eq!("100%25%zz%", normalize_percent_encoding("100%25%zz%"));
+    }
+
+    #[test]
+    fn significant_params() {
+        let significant = [String::from("id"), String::from("page_*")];
+        let retain = |url| retain_params(&Url::parse(url).unwrap(), &significant).to_string();
+
+        assert_eq!(
+            "https://example.com/item?id=1&page_size=2",
+            retain("https://example.com/item?ref=feed&id=1&utm_source=x&page_size=2")
+        );
+        assert_eq!(
+            "https://example.com/item",
+            retain("https://example.com/item?sort=asc")
+        );
+    }
+
+    #[test]
+    fn sorted_query() {
+        let sort = |url| sort_query(&Url::parse(url).unwrap()).to_string();
+
+        assert_eq!(
+            "https://example.com/?a=2&b=1&b=0",
+            sort("https://example.com/?b=1&a=2&b=0")
+        );
+        assert_eq!("https://example.com/", sort("https://example.com/"));
+    }
+
+    #[test]
+    fn query() {
+        let url = Url::parse("https://example.com/?q=%7e%2f").unwrap();
+
+        assert_eq!(Some("q=~%2F"), normalize_url(&url).query());
+    }
+}
@@ -0,0 +1,210 @@
+use std::{fmt::Debug, path::Path};
+
+use indicatif::{HumanBytes, ProgressBar};
+use log::{debug, error, info, warn};
+use reqwest::Url;
+
+use crate::{
+    scope::OutOfScope, Download, Error, STATUS_ERROR_STYLE, STATUS_OK_STYLE, STATUS_WARN_STYLE,
+};
+
+/// Receives the events of a crawl, all methods do nothing by default
+pub trait CrawlObserver: Debug + Send + Sync {
+    /// `url` was queued for download
+    fn on_enqueued(&self, _url: &Url) {}
+
+    /// The download of `url` started
+    fn on_started(&self, _url: &Url) {}
+
+    /// `url` was saved to `path` with `bytes` bytes
+    fn on_saved(&self, _url: &Url, _path: &Path, _bytes: u64) {}
+
+    /// The download of `url` finished
+    fn on_downloaded(&self, _url: &Url, _download: &Download) {}
+
+    /// `url` is not downloaded because of `reason`
+    fn on_skipped(&self, _url: &Url, _reason: &OutOfScope) {}
+
+    /// The download of `url` failed, it may be retried
+    fn on_error(&self, _url: &Url, _err: &Error) {}
+
+    /// `url` is not retried anymore after `failures` failed downloads
+    fn on_gave_up(&self, _url: &Url, _failures: usize) {}
+
+    /// `link` found on a page is not a valid url
+    fn on_invalid_link(&self, _link: &str, _err: &url::ParseError) {}
+
+    /// The crawl stops early because of `reason`
+    fn on_stopped(&self, _reason: &str) {}
+}
+
+/// Ignores all events
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopObserver;
+
+impl CrawlObserver for NoopObserver {}
+
+/// Prints the events above the progress bar of a worker
+#[derive(Debug, Clone)]
+pub struct ConsoleObserver {
+    progress_bar: ProgressBar,
+}
+
+impl ConsoleObserver {
+    pub fn new(progress_bar: ProgressBar) -> Self {
+        Self { progress_bar }
+    }
+
+    fn warn(&self, message: String) {
+        self.progress_bar.println(format!(
+            "{}: {message}",
+            STATUS_WARN_STYLE.apply_to("Warning")
+        ));
+    }
+
+    fn status(&self, status: &str, url: &Url) {
+        self.progress_bar
+            .println(format!("{:>13} {url}", STATUS_OK_STYLE.apply_to(status)));
+    }
+}
+
+impl CrawlObserver for ConsoleObserver {
+    fn on_downloaded(&self, url: &Url, download: &Download) {
+        match download {
+            Download::Saved => self.status("Saved", url),
+            Download::NotModified => self.status("Unchanged", url),
+            Download::Kept => self.status("Kept", url),
+            Download::Duplicate => self.status("Duplicate", url),
+            Download::Discarded => self.status("Discarded", url),
+            Download::OffsiteRedirect(location) => {
+                self.warn(format!(
+                    "Skipping {url}, it redirects off-site to {location}"
+                ));
+                self.status("Skipped", url);
+            }
+            Download::TooLarge { max_file_size } => {
+                self.warn(format!(
+                    "Skipping {url}, it is larger than {}",
+                    HumanBytes(*max_file_size)
+                ));
+                self.status("Skipped", url);
+            }
+            Download::ErrorStatus(status) => {
+                self.warn(format!("Skipping {url}, the server answered {status}"));
+                self.status("Skipped", url);
+            }
+            Download::DryRun(content_length) => {
+                let size = content_length.map_or_else(
+                    || String::from("unknown size"),
+                    |bytes| HumanBytes(bytes).to_string(),
+                );
+                self.progress_bar.println(format!(
+                    "{:>13} {url} ({size})",
+                    STATUS_OK_STYLE.apply_to("Would save"),
+                ));
+            }
+        }
+    }
+
+    fn on_skipped(&self, url: &Url, reason: &OutOfScope) {
+        match reason {
+            OutOfScope::PrivateAddress(_) => self.warn(format!("Skipping {url}, it {reason}")),
+            _ => self.warn(format!("Skipping {url}, {reason}")),
+        }
+    }
+
+    fn on_error(&self, url: &Url, err: &Error) {
+        self.progress_bar.println(format!(
+            "{} while downloading {url}: {err}",
+            STATUS_ERROR_STYLE.apply_to("Error"),
+        ));
+    }
+
+    fn on_gave_up(&self, url: &Url, failures: usize) {
+        self.progress_bar.println(format!(
+            "{} on {url} after {failures} failed attempts",
+            STATUS_ERROR_STYLE.apply_to("Giving up"),
+        ));
+    }
+
+    fn on_invalid_link(&self, link: &str, err: &url::ParseError) {
+        self.progress_bar.println(format!(
+            "{} parsing URL `{link}`: {err:?}",
+            STATUS_ERROR_STYLE.apply_to("Error"),
+        ));
+    }
+
+    fn on_stopped(&self, reason: &str) {
+        self.progress_bar.println(format!(
+            "{} crawl: {reason}",
+            STATUS_ERROR_STYLE.apply_to("Stopping"),
+        ));
+    }
+}
+
+/// Logs the events through the `log` crate
+///
+/// Saved files are logged at info level, queue operations at debug level.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogObserver;
+
+impl CrawlObserver for LogObserver {
+    fn on_enqueued(&self, url: &Url) {
+        debug!("Queued {url}");
+    }
+
+    fn on_started(&self, url: &Url) {
+        debug!("Dequeued {url}");
+    }
+
+    fn on_saved(&self, url: &Url, path: &Path, bytes: u64) {
+        debug!("Wrote {url} to {} ({})", path.display(), HumanBytes(bytes));
+    }
+
+    fn on_downloaded(&self, url: &Url, download: &Download) {
+        match download {
+            Download::Saved => info!("Saved {url}"),
+            Download::NotModified => info!("Unchanged {url}"),
+            Download::Kept => info!("Kept {url}"),
+            Download::Duplicate => info!("Duplicate {url}"),
+            Download::Discarded => info!("Discarded {url}"),
+            Download::OffsiteRedirect(location) => {
+                warn!("Skipping {url}, it redirects off-site to {location}")
+            }
+            Download::TooLarge { max_file_size } => warn!(
+                "Skipping {url}, it is larger than {}",
+                HumanBytes(*max_file_size)
+            ),
+            Download::ErrorStatus(status) => {
+                warn!("Skipping {url}, the server answered {status}")
+            }
+            Download::DryRun(Some(content_length)) => {
+                info!("Would save {url} ({})", HumanBytes(*content_length))
+            }
+            Download::DryRun(None) => info!("Would save {url} (unknown size)"),
+        }
+    }
+
+    fn on_skipped(&self, url: &Url, reason: &OutOfScope) {
+        match reason {
+            OutOfScope::PrivateAddress(_) => warn!("Skipping {url}, it {reason}"),
+            _ => warn!("Skipping {url}, {reason}"),
+        }
+    }
+
+    fn on_error(&self, url: &Url, err: &Error) {
+        error!("Error while downloading {url}: {err}");
+    }
+
+    fn on_gave_up(&self, url: &Url, failures: usize) {
+        error!("Giving up on {url} after {failures} failed attempts");
+    }
+
+    fn on_invalid_link(&self, link: &str, err: &url::ParseError) {
+        warn!("Skipping invalid link `{link}`: {err}");
+    }
+
+    fn on_stopped(&self, reason: &str) {
+        error!("Stopping crawl: {reason}");
+    }
+}
@@ -0,0 +1,219 @@
+use std::{
+    fs::{read_to_string, remove_file, write},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use reqwest::Url;
+use walkdir::WalkDir;
+
+use crate::{Error, Result};
+
+/// Suffix of files that are still being downloaded
+pub const PARTIAL_SUFFIX: &str = ".part";
+
+/// Suffix of the files recording the [`PartialInfo`] of a partial download
+pub const INFO_SUFFIX: &str = ".part.info";
+
+/// What to do with partial downloads left behind by an interrupted run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnPartial {
+    /// Keep the partial file and download the rest of it
+    Resume,
+    /// Delete the partial file
+    Discard,
+}
+
+impl Default for OnPartial {
+    fn default() -> Self {
+        Self::Discard
+    }
+}
+
+impl FromStr for OnPartial {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "resume" => Ok(Self::Resume),
+            "discard" => Ok(Self::Discard),
+            _ => Err(format!("expected `resume` or `discard`, got `{s}`")),
+        }
+    }
+}
+
+/// Path a download to `path` is written to until it is complete
+pub fn partial_path(path: &Path) -> PathBuf {
+    let mut partial_path = path.as_os_str().to_os_string();
+    partial_path.push(PARTIAL_SUFFIX);
+    partial_path.into()
+}
+
+/// Path the [`PartialInfo`] of a partial download of `path` is kept at
+pub fn info_path(path: &Path) -> PathBuf {
+    let mut info_path = path.as_os_str().to_os_string();
+    info_path.push(INFO_SUFFIX);
+    info_path.into()
+}
+
+/// Whether `path` is a partial download or the info of one
+pub fn is_partial_file(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+
+    path.ends_with(PARTIAL_SUFFIX) || path.ends_with(INFO_SUFFIX)
+}
+
+/// Where a partial download came from, kept next to it
+///
+/// Files are not always saved at paths the url can be told from, like hashed
+/// or sharded ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialInfo {
+    pub url: Url,
+    /// `ETag` or `Last-Modified` value of the response, the rest is only
+    /// requested if it did not change
+    pub validator: Option<String>,
+}
+
+impl PartialInfo {
+    /// Reads the info of the partial download of `path`, if it has one
+    pub fn read(path: &Path) -> Option<Self> {
+        let info = read_to_string(info_path(path)).ok()?;
+        let mut lines = info.lines();
+        let url = Url::parse(lines.next()?).ok()?;
+        let validator = lines.next().map(str::to_string);
+
+        Some(Self { url, validator })
+    }
+
+    /// Keeps the info next to the partial download of `path`
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut info = format!("{}\n", self.url);
+        if let Some(validator) = &self.validator {
+            info.push_str(validator);
+            info.push('\n');
+        }
+
+        write(info_path(path), info).map_err(Error::WriteFile)
+    }
+}
+
+/// Applies `policy` to the partial downloads in `output_path`.
+///
+/// Only partial downloads with a [`PartialInfo`] are found, other `.part`
+/// files are temporaries like moved files. Returns the urls of the partial
+/// downloads which should be resumed.
+pub fn handle_partial_files(output_path: &Path, policy: OnPartial) -> Result<Vec<Url>> {
+    let mut resumable = Vec::new();
+
+    let info_files = WalkDir::new(output_path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.to_string_lossy().ends_with(INFO_SUFFIX));
+
+    for info_file in info_files {
+        let info_file = info_file.to_string_lossy();
+        let path = Path::new(info_file.trim_end_matches(INFO_SUFFIX));
+
+        match (policy, PartialInfo::read(path)) {
+            (OnPartial::Resume, Some(info)) if partial_path(path).is_file() => {
+                resumable.push(info.url)
+            }
+            _ => {
+                for path in [partial_path(path), info_path(path)] {
+                    match remove_file(path) {
+                        Err(err) if err.kind() != ErrorKind::NotFound => {
+                            return Err(Error::RemoveFile(err))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(resumable)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{create_dir_all, write};
+
+    use super::*;
+
+    fn output_with_partial_file() -> tempfile::TempDir {
+        let output = tempfile::tempdir().unwrap();
+        let dir = output.path().join("example.com/_hashed");
+        create_dir_all(&dir).unwrap();
+        write(output.path().join("example.com/index.html"), "done").unwrap();
+        write(dir.join("0a1b.bin.part"), "half").unwrap();
+        PartialInfo {
+            url: Url::parse("https://example.com/files/large.bin").unwrap(),
+            validator: Some(String::from(r#""v1""#)),
+        }
+        .write(&dir.join("0a1b.bin"))
+        .unwrap();
+        // left by moving a file into a directory
+        write(output.path().join("example.com/files.part"), "done").unwrap();
+        output
+    }
+
+    #[test]
+    fn discard() {
+        let output = output_with_partial_file();
+
+        let resumable = handle_partial_files(output.path(), OnPartial::Discard).unwrap();
+
+        assert!(resumable.is_empty());
+        assert!(!output
+            .path()
+            .join("example.com/_hashed/0a1b.bin.part")
+            .exists());
+        assert!(!output
+            .path()
+            .join("example.com/_hashed/0a1b.bin.part.info")
+            .exists());
+        assert!(output.path().join("example.com/index.html").exists());
+        assert!(output.path().join("example.com/files.part").exists());
+    }
+
+    #[test]
+    fn resume() {
+        let output = output_with_partial_file();
+
+        let resumable = handle_partial_files(output.path(), OnPartial::Resume).unwrap();
+
+        assert_eq!(
+            vec![Url::parse("https://example.com/files/large.bin").unwrap()],
+            resumable
+        );
+        assert!(output
+            .path()
+            .join("example.com/_hashed/0a1b.bin.part")
+            .exists());
+    }
+
+    #[test]
+    fn info() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        let info = PartialInfo {
+            url: Url::parse("https://example.com/file.txt").unwrap(),
+            validator: None,
+        };
+
+        assert_eq!(None, PartialInfo::read(&path));
+        info.write(&path).unwrap();
+        assert_eq!(Some(info), PartialInfo::read(&path));
+    }
+
+    #[test]
+    fn parse_policy() {
+        assert_eq!(Ok(OnPartial::Resume), "resume".parse());
+        assert_eq!(Ok(OnPartial::Discard), "Discard".parse());
+        assert!("keep".parse::<OnPartial>().is_err());
+    }
+}
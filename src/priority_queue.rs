@@ -1,17 +1,41 @@
-use std::sync::Arc;
+use std::{
+    fmt::{Debug, Display},
+    fs::{create_dir_all, read_to_string, File, OpenOptions},
+    hash::Hash,
+    io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+};
 
 use crossbeam_queue::SegQueue;
-use dashmap::DashMap;
+use dashmap::{mapref::entry::Entry, DashMap};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// Elements kept in memory per priority before spilling to disk
+pub const DEFAULT_SPILL_CAPACITY: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Priority {
+    High,
     Normal,
     Low,
 }
 
 impl Priority {
     fn len() -> usize {
-        2
+        3
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::High => "high",
+            Self::Normal => "normal",
+            Self::Low => "low",
+        }
     }
 }
 
@@ -25,39 +49,471 @@ impl Default for Priority {
 #[derive(Debug, Clone)]
 pub struct PriorityQueue<T> {
     queues: DashMap<Priority, Arc<SegQueue<T>>>,
+    /// Takes the elements not fitting into memory
+    spill: Option<Arc<dyn Spill<T>>>,
+    /// Elements currently queued, to skip pushing them again
+    queued: Option<Arc<dyn Queued<T>>>,
+}
+
+impl<T> Default for PriorityQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T> PriorityQueue<T> {
     pub fn new() -> Self {
         let queues = DashMap::with_capacity(Priority::len());
+        queues.insert(Priority::High, Arc::new(SegQueue::new()));
         queues.insert(Priority::Normal, Arc::new(SegQueue::new()));
         queues.insert(Priority::Low, Arc::new(SegQueue::new()));
 
-        Self { queues }
+        Self {
+            queues,
+            spill: None,
+            queued: None,
+        }
     }
 
     fn pop_priority(&self, priority: Priority) -> Option<T> {
-        self.queues
-            .get(&priority)
-            .map(|queue| queue.pop())
-            .flatten()
+        let queue = self.queues.get(&priority)?;
+
+        queue.pop().or_else(|| {
+            let spill = self.spill.as_ref()?;
+            spill
+                .refill(priority)
+                .into_iter()
+                // spilled elements may have been queued again meanwhile
+                .filter(|value| {
+                    self.queued
+                        .as_ref()
+                        .map_or(true, |queued| queued.insert(value, priority))
+                })
+                .for_each(|value| queue.push(value));
+            queue.pop()
+        })
     }
 
     pub fn pop(&self) -> Option<T> {
-        self.pop_priority(Priority::Normal)
-            .or_else(|| self.pop_priority(Priority::Low))
+        let value = self
+            .pop_priority(Priority::High)
+            .or_else(|| self.pop_priority(Priority::Normal))
+            .or_else(|| self.pop_priority(Priority::Low))?;
+
+        if let Some(queued) = &self.queued {
+            queued.remove(&value);
+        }
+
+        Some(value)
     }
 
     pub fn push<P>(&self, value: T, priority: P)
     where
         P: Into<Option<Priority>>,
     {
-        self.queues
-            .get(&priority.into().unwrap_or_default())
-            .map(|queue| queue.push(value));
+        let priority = priority.into().unwrap_or_default();
+
+        if let Some(queue) = self.queues.get(&priority) {
+            let value = match &self.spill {
+                Some(spill) => match spill.push(priority, queue.len(), value) {
+                    Some(value) => value,
+                    None => return,
+                },
+                None => value,
+            };
+
+            // only the elements in memory are deduplicated to keep the memory
+            // use bounded while spilling
+            if let Some(queued) = &self.queued {
+                if !queued.insert(&value, priority) {
+                    return;
+                }
+            }
+
+            queue.push(value)
+        }
+    }
+
+    /// Number of queued elements, including spilled ones.
+    ///
+    /// Only approximate while other threads push or pop.
+    pub fn len(&self) -> usize {
+        [Priority::High, Priority::Normal, Priority::Low]
+            .into_iter()
+            .map(|priority| self.len_by_priority(priority))
+            .sum()
+    }
+
+    /// Number of queued elements of `priority`, approximate like [`Self::len`]
+    pub fn len_by_priority(&self, priority: Priority) -> usize {
+        let queued = self.queues.get(&priority).map_or(0, |queue| queue.len());
+        let spilled = self.spill.as_ref().map_or(0, |spill| spill.len(priority));
+
+        queued + spilled
     }
 
     pub fn is_empty(&self) -> bool {
-        self.queues.iter().all(|queue| queue.is_empty())
+        self.len() == 0
+    }
+}
+
+impl<T> PriorityQueue<T>
+where
+    T: Eq + Hash + Clone + Debug + Send + Sync + 'static,
+{
+    /// Skips pushing elements which are already queued, at any priority
+    ///
+    /// Spilled elements are not deduplicated, they may come out more than once.
+    pub fn with_deduplication(self) -> Self {
+        Self {
+            queued: Some(Arc::new(DashMap::new())),
+            ..self
+        }
+    }
+
+    /// Queued elements in memory with their priorities
+    ///
+    /// Only known with deduplication, empty otherwise. Spilled elements stay in
+    /// their files and are picked up from there again.
+    pub fn elements(&self) -> Vec<(T, Priority)> {
+        self.queued
+            .as_ref()
+            .map_or_else(Vec::new, |queued| queued.elements())
+    }
+}
+
+impl<T> PriorityQueue<T>
+where
+    T: Display + FromStr + Send + 'static,
+{
+    /// Keeps at most `capacity` elements per priority in memory and spills
+    /// the rest to files in `dir`.
+    ///
+    /// Elements spilled by an earlier queue in `dir` are picked up again.
+    pub fn with_disk_spill<P: Into<PathBuf>>(dir: P, capacity: usize) -> io::Result<Self> {
+        Ok(Self {
+            spill: Some(Arc::new(DiskSpill::open(dir.into(), capacity)?)),
+            ..Self::new()
+        })
+    }
+}
+
+/// Storage for the elements exceeding the in-memory capacity
+trait Spill<T>: Debug + Send + Sync {
+    /// Takes `value` unless it fits into the in-memory queue of length `queued`
+    fn push(&self, priority: Priority, queued: usize, value: T) -> Option<T>;
+
+    /// Takes spilled elements of `priority` to move them into memory
+    fn refill(&self, priority: Priority) -> Vec<T>;
+
+    /// Number of spilled elements of `priority`
+    fn len(&self, priority: Priority) -> usize;
+}
+
+/// Set of the queued elements with their priorities
+trait Queued<T>: Debug + Send + Sync {
+    /// Returns whether `value` was not queued yet
+    fn insert(&self, value: &T, priority: Priority) -> bool;
+
+    fn remove(&self, value: &T);
+
+    fn elements(&self) -> Vec<(T, Priority)>;
+}
+
+impl<T> Queued<T> for DashMap<T, Priority>
+where
+    T: Eq + Hash + Clone + Debug + Send + Sync,
+{
+    fn insert(&self, value: &T, priority: Priority) -> bool {
+        match self.entry(value.clone()) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(priority);
+                true
+            }
+        }
+    }
+
+    fn remove(&self, value: &T) {
+        DashMap::remove(self, value);
+    }
+
+    fn elements(&self) -> Vec<(T, Priority)> {
+        self.iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect()
+    }
+}
+
+/// Spills elements as lines to one file per priority
+struct DiskSpill<T> {
+    capacity: usize,
+    files: DashMap<Priority, Mutex<SpillFile>>,
+    elements: PhantomData<fn(T) -> T>,
+}
+
+/// Append-only file of spilled elements read from the front
+#[derive(Debug)]
+struct SpillFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// Byte offset of the first element not read yet
+    offset: u64,
+    len: usize,
+}
+
+impl SpillFile {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .read(true)
+            .open(&path)?;
+
+        let offset = read_to_string(path.with_extension("offset"))
+            .ok()
+            .and_then(|offset| offset.trim().parse().ok())
+            .unwrap_or(0);
+
+        let mut reader = BufReader::new(File::open(&path)?);
+        reader.seek(SeekFrom::Start(offset))?;
+        let len = reader.lines().count();
+
+        Ok(Self {
+            path,
+            writer: BufWriter::new(file),
+            offset,
+            len,
+        })
+    }
+
+    fn push(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.writer, "{line}")?;
+        // survive crashes for resuming
+        self.writer.flush()?;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Reads up to `count` lines from the front
+    fn pop(&mut self, count: usize) -> io::Result<Vec<String>> {
+        if self.len == 0 {
+            return Ok(Vec::new());
+        }
+
+        self.writer.flush()?;
+
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        reader.seek(SeekFrom::Start(self.offset))?;
+
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        while lines.len() < count && reader.read_line(&mut line)? > 0 {
+            self.offset += line.len() as u64;
+            lines.push(line.trim_end_matches('\n').to_string());
+            line.clear();
+        }
+        self.len -= lines.len().min(self.len);
+
+        if self.len == 0 {
+            // start over instead of growing forever
+            self.writer.get_ref().set_len(0)?;
+            self.offset = 0;
+        }
+        std::fs::write(self.path.with_extension("offset"), self.offset.to_string())?;
+
+        Ok(lines)
+    }
+}
+
+impl<T> Debug for DiskSpill<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiskSpill")
+            .field("capacity", &self.capacity)
+            .field("files", &self.files)
+            .finish()
+    }
+}
+
+impl<T> DiskSpill<T> {
+    fn open(dir: PathBuf, capacity: usize) -> io::Result<Self> {
+        create_dir_all(&dir)?;
+
+        let files = DashMap::with_capacity(Priority::len());
+        for priority in [Priority::High, Priority::Normal, Priority::Low] {
+            let path = dir.join(format!("{}.queue", priority.name()));
+            files.insert(priority, Mutex::new(SpillFile::open(path)?));
+        }
+
+        Ok(Self {
+            capacity,
+            files,
+            elements: PhantomData,
+        })
+    }
+}
+
+impl<T> Spill<T> for DiskSpill<T>
+where
+    T: Display + FromStr + Send + 'static,
+{
+    fn push(&self, priority: Priority, queued: usize, value: T) -> Option<T> {
+        let file = self.files.get(&priority)?;
+        let mut file = file.lock();
+
+        // keep the order once elements were spilled
+        if queued < self.capacity && file.len == 0 {
+            return Some(value);
+        }
+
+        match file.push(&value.to_string()) {
+            Ok(()) => None,
+            // keep it in memory rather than losing it
+            Err(_) => Some(value),
+        }
+    }
+
+    fn refill(&self, priority: Priority) -> Vec<T> {
+        self.files.get(&priority).map_or_else(Vec::new, |file| {
+            file.lock()
+                .pop(self.capacity)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|line| line.parse().ok())
+                .collect()
+        })
+    }
+
+    fn len(&self, priority: Priority) -> usize {
+        self.files.get(&priority).map_or(0, |file| file.lock().len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn high_first() {
+        let queue = PriorityQueue::new();
+
+        queue.push(0, Priority::Low);
+        queue.push(1, Priority::Normal);
+        queue.push(2, Priority::High);
+
+        assert_eq!(
+            vec![2, 1, 0],
+            std::iter::from_fn(|| queue.pop()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn deduplication() {
+        let queue = PriorityQueue::new().with_deduplication();
+
+        queue.push(0, Priority::Normal);
+        queue.push(0, Priority::High);
+        queue.push(1, Priority::Low);
+
+        assert_eq!(2, queue.len());
+        assert_eq!(Some(0), queue.pop());
+
+        // may be queued again once popped
+        queue.push(0, Priority::Normal);
+        assert_eq!(
+            vec![0, 1],
+            std::iter::from_fn(|| queue.pop()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn elements() {
+        let queue = PriorityQueue::new().with_deduplication();
+
+        queue.push(0, Priority::Low);
+        queue.push(1, Priority::High);
+        queue.push(1, Priority::Normal);
+        queue.push(2, None);
+        queue.pop();
+
+        let mut elements = queue.elements();
+        elements.sort();
+        assert_eq!(vec![(0, Priority::Low), (2, Priority::Normal)], elements);
+    }
+
+    #[test]
+    fn spill_to_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = PriorityQueue::<u32>::with_disk_spill(dir.path(), 2).unwrap();
+
+        for value in 0..5 {
+            queue.push(value, Priority::Low);
+        }
+        for value in 10..15 {
+            queue.push(value, Priority::Normal);
+        }
+
+        assert_eq!(10, queue.len());
+        assert_eq!(5, queue.len_by_priority(Priority::Normal));
+        assert_eq!(0, queue.len_by_priority(Priority::High));
+        assert!(dir.path().join("normal.queue").metadata().unwrap().len() > 0);
+        assert_eq!(
+            vec![10, 11, 12, 13, 14, 0, 1, 2, 3, 4],
+            std::iter::from_fn(|| queue.pop()).collect::<Vec<_>>()
+        );
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn spill_deduplication() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = PriorityQueue::<u32>::with_disk_spill(dir.path(), 2)
+            .unwrap()
+            .with_deduplication();
+
+        for value in 0..4 {
+            queue.push(value, None);
+        }
+
+        // only the elements in memory are known
+        let mut elements = queue.elements();
+        elements.sort();
+        assert_eq!(vec![(0, Priority::Normal), (1, Priority::Normal)], elements);
+        assert_eq!(4, queue.len());
+
+        assert_eq!(
+            vec![0, 1, 2],
+            std::iter::from_fn(|| queue.pop())
+                .take(3)
+                .collect::<Vec<_>>()
+        );
+        // refilled elements are deduplicated again
+        assert_eq!(vec![(3, Priority::Normal)], queue.elements());
+        queue.push(3, None);
+        assert_eq!(
+            vec![3],
+            std::iter::from_fn(|| queue.pop()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn resume_spilled() {
+        let dir = tempfile::tempdir().unwrap();
+
+        {
+            let queue = PriorityQueue::<u32>::with_disk_spill(dir.path(), 1).unwrap();
+            for value in 0..4 {
+                queue.push(value, None);
+            }
+            assert_eq!(Some(0), queue.pop());
+            assert_eq!(Some(1), queue.pop());
+        }
+
+        let queue = PriorityQueue::<u32>::with_disk_spill(dir.path(), 1).unwrap();
+
+        assert_eq!(
+            vec![2, 3],
+            std::iter::from_fn(|| queue.pop()).collect::<Vec<_>>()
+        );
     }
 }
@@ -0,0 +1,316 @@
+use std::{
+    collections::HashMap,
+    fs::{create_dir_all, read, write},
+    path::{Path, PathBuf},
+};
+
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+
+use crate::{
+    charset, convert, document_base,
+    escape_path::QUERY_SEPARATOR,
+    long_paths::HashedPaths,
+    mime_ext,
+    rewrite_map::{self, RewriteMap},
+    sniffs_html, Error, Result, Settings,
+};
+
+/// Copies the files of the mirror in `source` to the paths `settings` would
+/// save them to, without requesting anything.
+///
+/// The urls are taken from the rewrite map of `source`, so it must have been
+/// crawled with [`Settings::generate_rewrite_map`]. Files keep the extension
+/// appended for their content type. With [`Settings::convert_links`] the links
+/// of pages are pointed to the new paths, whether they were converted for the
+/// old layout or not. The new layout is the one of the path options like
+/// [`Settings::query_layout`], free-form path templates are not supported.
+/// Returns the number of copied files.
+pub fn relayout(source: &Path, settings: &Settings) -> Result<usize> {
+    let hashed_paths = HashedPaths::default();
+    let rewrite_map = RewriteMap::default();
+
+    let mut files = Vec::new();
+    for (key, file) in rewrite_map::read(source)? {
+        // the scheme and port do not affect the path
+        let url = match Url::parse(&format!("http://{key}")) {
+            Ok(url) => url,
+            Err(_) => continue,
+        };
+        let from = PathBuf::from(file.trim_start_matches('/'));
+        let mut path = match settings.file_path(&url, &hashed_paths) {
+            Some(path) => path,
+            None => continue,
+        };
+        if let Some(extension) = appended_extension(&from, &path, &url) {
+            let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+            file_name.push(format!(".{extension}"));
+            path.set_file_name(file_name);
+        }
+
+        files.push((key, url, from, path));
+    }
+
+    // converted links point to the old paths, others to the urls
+    let by_path = files
+        .iter()
+        .map(|(_, _, from, path)| (from.as_path(), path.as_path()))
+        .collect::<HashMap<_, _>>();
+    let by_key = files
+        .iter()
+        .map(|(key, _, _, path)| (key.as_str(), path.as_path()))
+        .collect::<HashMap<_, _>>();
+
+    for (_, url, from, path) in &files {
+        let mut contents = read(source.join(from)).map_err(Error::ReadFile)?;
+        if settings.convert_links && is_page(from, &contents) {
+            let (document, encoding, _) = charset::detect(None, &contents).decode(&contents);
+            let converted = convert_links(&document, url, from, path, &by_path, &by_key)?;
+            contents = encoding.encode(&converted).0.into_owned();
+        }
+
+        let output_path = settings.output_path.join(path);
+        if let Some(parent) = output_path.parent() {
+            create_dir_all(parent).map_err(Error::CreateFile)?;
+        }
+        write(&output_path, contents).map_err(Error::WriteFile)?;
+
+        rewrite_map.insert(url, path);
+    }
+
+    rewrite_map.write(&settings.output_path)?;
+    if !hashed_paths.is_empty() {
+        hashed_paths.write_manifest(&settings.output_path)?;
+    }
+
+    Ok(files.len())
+}
+
+/// Extension appended to the file at `from` for the content type of `url`,
+/// which the new `path` needs as well
+fn appended_extension<'a>(from: &'a Path, path: &Path, url: &Url) -> Option<&'a str> {
+    if mime_ext::has_extension(url) {
+        return None;
+    }
+
+    let extension = from.extension()?.to_str()?;
+    let known = mime_ext::extensions().any(|known| known == extension);
+    let missing = path.extension().and_then(|ext| ext.to_str()) != Some(extension);
+
+    (known && missing).then(|| extension)
+}
+
+/// Whether the file at `path` with `contents` is a page whose links are
+/// converted
+fn is_page(path: &Path, contents: &[u8]) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    // the query is part of the file name
+    let name = name.split(QUERY_SEPARATOR).next().unwrap_or_default();
+    let extension = name.rsplit_once('.').map(|(_, extension)| extension);
+
+    mime_ext::mime_type(extension.unwrap_or_default()) == Some("text/html") || sniffs_html(contents)
+}
+
+/// Points the links of `document`, the page of `url` copied from `from` to
+/// `to`, to the new paths of the files they link to
+fn convert_links(
+    document: &str,
+    url: &Url,
+    from: &Path,
+    to: &Path,
+    by_path: &HashMap<&Path, &Path>,
+    by_key: &HashMap<&str, &Path>,
+) -> Result<String> {
+    let dom = tl::parse(document, tl::ParserOptions::default())?;
+    let base_url = document_base(&dom, url);
+
+    let replacements = convert::link_spans(&dom, document)
+        .into_iter()
+        .filter_map(|span| {
+            let link = &document[span.clone()];
+            let (target, fragment) = match link.split_once('#') {
+                Some((target, fragment)) => (target, Some(fragment)),
+                None => (link, None),
+            };
+
+            let path = linked_path(from, target)
+                .and_then(|path| by_path.get(path.as_path()).copied())
+                .or_else(|| {
+                    let key = rewrite_map::request_key(&base_url.join(target).ok()?)?;
+                    by_key.get(key.as_str()).copied()
+                })?;
+
+            let mut converted = convert::relative_link(to, path);
+            if let Some(fragment) = fragment {
+                converted = format!("{converted}#{fragment}");
+            }
+
+            Some((span, converted))
+        })
+        .collect();
+
+    Ok(convert::replace(document, replacements))
+}
+
+/// Path relative to the output directory of the file which the converted
+/// `link` in the file at `from` points to
+fn linked_path(from: &Path, link: &str) -> Option<PathBuf> {
+    // converted links are relative and escape the query separator of file
+    // names where it is a `?`, so a raw `?` starts the query of a url
+    if link.is_empty() || link.starts_with('/') || link.contains('?') || Url::parse(link).is_ok() {
+        return None;
+    }
+
+    let link = percent_decode_str(link).decode_utf8().ok()?;
+    let mut path = from.parent()?.to_path_buf();
+    for segment in link.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                if !path.pop() {
+                    return None;
+                }
+            }
+            segment => path.push(segment),
+        }
+    }
+
+    Some(path)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{read, read_to_string, write};
+
+    use super::*;
+    use crate::QueryLayout;
+
+    #[test]
+    fn query_layout() {
+        let source = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+
+        let crawled = Settings::builder()
+            .output_path(source.path())
+            .targets(vec![])
+            .build();
+        let pages = ["https://example.com/?page=2", "https://example.com/about"];
+        let map = RewriteMap::default();
+        for (index, page) in pages.iter().enumerate() {
+            let url = Url::parse(page).unwrap();
+            let path = crawled.file_path(&url, &HashedPaths::default()).unwrap();
+            create_dir_all(source.path().join(&path).parent().unwrap()).unwrap();
+            write(source.path().join(&path), [index as u8, 0xff]).unwrap();
+            map.insert(&url, &path);
+        }
+        map.write(source.path()).unwrap();
+
+        let settings = Settings::builder()
+            .output_path(output.path())
+            .targets(vec![])
+            .query_layout(QueryLayout::Directory)
+            .build();
+
+        assert_eq!(2, relayout(source.path(), &settings).unwrap());
+
+        for (index, page) in pages.iter().enumerate() {
+            let url = Url::parse(page).unwrap();
+            let path = settings.url_to_path(&url).unwrap();
+            assert_eq!(
+                vec![index as u8, 0xff],
+                read(output.path().join(path)).unwrap()
+            );
+        }
+        assert_ne!(
+            crawled.url_to_path(&Url::parse(pages[0]).unwrap()),
+            settings.url_to_path(&Url::parse(pages[0]).unwrap())
+        );
+        assert_eq!(2, rewrite_map::read(output.path()).unwrap().len());
+    }
+
+    #[test]
+    fn converted_links() {
+        let source = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+
+        let crawled = Settings::builder()
+            .output_path(source.path())
+            .targets(vec![])
+            .build();
+        let index = Url::parse("https://example.com/").unwrap();
+        let results = Url::parse("https://example.com/search/?q=cat").unwrap();
+        let avatar = Url::parse("https://example.com/avatar?id=42").unwrap();
+        let path = |url: &Url| crawled.file_path(url, &HashedPaths::default()).unwrap();
+        // saved for the content type image/png
+        let avatar_path = PathBuf::from(format!("example.com/avatar{QUERY_SEPARATOR}id=42.png"));
+        let files = [
+            (
+                &index,
+                path(&index),
+                format!(
+                    r#"<a href="{}#top">results</a><img src="{}">"#,
+                    convert::relative_link(&path(&index), &path(&results)),
+                    convert::relative_link(&path(&index), &avatar_path)
+                ),
+            ),
+            // not converted
+            (
+                &results,
+                path(&results),
+                String::from(r#"<a href="/">home</a>"#),
+            ),
+            (&avatar, avatar_path, String::from("png")),
+        ];
+        let map = RewriteMap::default();
+        for (url, path, contents) in &files {
+            create_dir_all(source.path().join(path).parent().unwrap()).unwrap();
+            write(source.path().join(path), contents).unwrap();
+            map.insert(url, path);
+        }
+        map.write(source.path()).unwrap();
+
+        let settings = Settings::builder()
+            .output_path(output.path())
+            .targets(vec![])
+            .query_layout(QueryLayout::Directory)
+            .convert_links(true)
+            .build();
+
+        assert_eq!(3, relayout(source.path(), &settings).unwrap());
+
+        let new_path = |url: &Url| settings.url_to_path(url).unwrap();
+        let new_avatar_path = PathBuf::from(format!("{}.png", new_path(&avatar).to_string_lossy()));
+        assert_eq!(
+            "png",
+            read_to_string(output.path().join(&new_avatar_path)).unwrap()
+        );
+        assert_ne!(path(&results), new_path(&results));
+        assert_eq!(
+            format!(
+                r#"<a href="{}#top">results</a><img src="{}">"#,
+                convert::relative_link(&new_path(&index), &new_path(&results)),
+                convert::relative_link(&new_path(&index), &new_avatar_path)
+            ),
+            read_to_string(output.path().join(new_path(&index))).unwrap()
+        );
+        assert_eq!(
+            format!(
+                r#"<a href="{}">home</a>"#,
+                convert::relative_link(&new_path(&results), &new_path(&index))
+            ),
+            read_to_string(output.path().join(new_path(&results))).unwrap()
+        );
+    }
+
+    #[test]
+    fn pages_with_queries() {
+        let page = format!("example.com/search/index.html{QUERY_SEPARATOR}q=cat");
+        let image = format!("example.com/avatar{QUERY_SEPARATOR}id=42.png");
+
+        assert!(is_page(Path::new(&page), b"results"));
+        assert!(!is_page(Path::new(&image), b"png"));
+    }
+}
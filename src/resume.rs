@@ -0,0 +1,246 @@
+use std::{
+    fs::{read_to_string, remove_file, rename, File},
+    io::{BufWriter, ErrorKind, Write},
+    path::Path,
+};
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    priority_queue::{Priority, PriorityQueue},
+    state::State,
+    visited::VisitedStore,
+    Error, Result,
+};
+
+/// Name of the file holding the progress of an unfinished crawl in the output
+/// directory
+pub const STATE_FILE_NAME: &str = ".wmt-state.json";
+
+/// Version of the state file format, bumped on incompatible changes
+pub const STATE_VERSION: u32 = 2;
+
+/// Progress of a crawl to pick it up again after a restart
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrawlSnapshot {
+    pub version: u32,
+    /// Urls which were checked already
+    pub checked: Vec<Url>,
+    /// Urls whose files were saved
+    pub downloaded: Vec<Url>,
+    /// Queued urls with their priority and link hops from the targets, which
+    /// targets have none of
+    pub pending: Vec<(Url, Priority, Option<usize>)>,
+}
+
+/// Just the version, read before the rest of the file
+#[derive(Deserialize)]
+struct Versioned {
+    version: u32,
+}
+
+impl CrawlSnapshot {
+    /// Takes the progress of the crawl sharing `state` and `priority_queue`
+    /// so far
+    ///
+    /// Stores which persist themselves, like the disk store, add no urls. The
+    /// queue has to deduplicate to know its urls, spilled ones are picked up
+    /// from the spill directory instead. Urls which workers are busy with are
+    /// pending as well.
+    pub fn capture(
+        checked: &dyn VisitedStore,
+        downloaded: &dyn VisitedStore,
+        state: &State,
+        priority_queue: &PriorityQueue<Url>,
+    ) -> Self {
+        let mut pending = priority_queue
+            .elements()
+            .into_iter()
+            .map(|(url, priority)| {
+                let depth = state.depths.get(&url).map(|depth| *depth);
+                (url, priority, depth)
+            })
+            .collect::<Vec<_>>();
+        // taken from the queue but not done yet, a worker puts them back on
+        // failure with normal priority
+        let in_flight = state
+            .in_flight
+            .iter()
+            .map(|entry| entry.key().clone())
+            .filter(|url| !checked.contains(url))
+            .filter(|url| pending.iter().all(|(pending, ..)| pending != url))
+            .map(|url| {
+                let depth = state.depths.get(&url).map(|depth| *depth);
+                (url, Priority::Normal, depth)
+            })
+            .collect::<Vec<_>>();
+        pending.extend(in_flight);
+        pending.sort();
+
+        let mut checked = checked.urls();
+        checked.sort();
+        let mut downloaded = downloaded.urls();
+        downloaded.sort();
+
+        Self {
+            version: STATE_VERSION,
+            checked,
+            downloaded,
+            pending,
+        }
+    }
+
+    /// Loads the state file of an unfinished crawl in `output_path`, if any
+    pub fn load(output_path: &Path) -> Result<Option<Self>> {
+        let json = match read_to_string(output_path.join(STATE_FILE_NAME)) {
+            Ok(json) => json,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::ReadFile(err)),
+        };
+
+        let Versioned { version } = serde_json::from_str(&json).map_err(Error::ParseState)?;
+        if version != STATE_VERSION {
+            return Err(Error::StateVersion { version });
+        }
+
+        serde_json::from_str(&json)
+            .map(Some)
+            .map_err(Error::ParseState)
+    }
+
+    /// Writes the state file to `output_path`, or removes it if nothing is
+    /// left to do
+    pub fn save(&self, output_path: &Path) -> Result<()> {
+        let path = output_path.join(STATE_FILE_NAME);
+        if self.pending.is_empty() {
+            return match remove_file(&path) {
+                Err(err) if err.kind() != ErrorKind::NotFound => Err(Error::RemoveFile(err)),
+                _ => Ok(()),
+            };
+        }
+
+        // a crash while writing keeps the previous state
+        let temp_path = output_path.join(format!("{STATE_FILE_NAME}.tmp"));
+        let file = File::create(&temp_path).map_err(Error::CreateFile)?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, self).map_err(|err| Error::WriteFile(err.into()))?;
+        writer.flush().map_err(Error::WriteFile)?;
+
+        rename(&temp_path, &path).map_err(Error::WriteFile)
+    }
+
+    /// Continues the crawl sharing `state` where the snapshot left off
+    pub fn restore(
+        self,
+        checked: &dyn VisitedStore,
+        downloaded: &dyn VisitedStore,
+        state: &State,
+        priority_queue: &PriorityQueue<Url>,
+    ) {
+        for url in self.checked {
+            checked.insert(url);
+        }
+        for url in self.downloaded {
+            downloaded.insert(url);
+        }
+        for (url, priority, depth) in self.pending {
+            if let Some(depth) = depth {
+                state.depths.insert(url.clone(), depth);
+            }
+            priority_queue.push(url, priority);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use dashmap::DashSet;
+
+    use super::*;
+
+    fn url(path: &str) -> Url {
+        Url::parse("https://example.com/")
+            .unwrap()
+            .join(path)
+            .unwrap()
+    }
+
+    #[test]
+    fn roundtrip() {
+        let output = tempfile::tempdir().unwrap();
+        let checked = DashSet::new();
+        let downloaded = DashSet::new();
+        let state = State::default();
+        let priority_queue = PriorityQueue::new().with_deduplication();
+
+        checked.insert(url("/"));
+        downloaded.insert(url("/"));
+        state.depths.insert(url("/about"), 1);
+        state.depths.insert(url("/style.css"), 1);
+        // a redirect target, checked without being queued
+        state.depths.insert(url("/moved"), 1);
+        priority_queue.push(url("/about"), None);
+        priority_queue.push(url("/style.css"), Priority::High);
+
+        let snapshot = CrawlSnapshot::capture(&checked, &downloaded, &state, &priority_queue);
+        assert_eq!(
+            vec![
+                (url("/about"), Priority::Normal, Some(1)),
+                (url("/style.css"), Priority::High, Some(1)),
+            ],
+            snapshot.pending
+        );
+        snapshot.save(output.path()).unwrap();
+
+        let checked = DashSet::new();
+        let downloaded = DashSet::new();
+        let state = State::default();
+        let priority_queue = PriorityQueue::new();
+        CrawlSnapshot::load(output.path())
+            .unwrap()
+            .unwrap()
+            .restore(&checked, &downloaded, &state, &priority_queue);
+
+        assert!(checked.contains(&url("/")));
+        assert!(downloaded.contains(&url("/")));
+        assert_eq!(
+            Some(1),
+            state.depths.get(&url("/about")).map(|depth| *depth)
+        );
+        assert_eq!(Some(url("/style.css")), priority_queue.pop());
+        assert_eq!(Some(url("/about")), priority_queue.pop());
+    }
+
+    #[test]
+    fn finished_crawl() {
+        let output = tempfile::tempdir().unwrap();
+        std::fs::write(output.path().join(STATE_FILE_NAME), "{}").unwrap();
+
+        CrawlSnapshot::capture(
+            &DashSet::new(),
+            &DashSet::new(),
+            &State::default(),
+            &PriorityQueue::new().with_deduplication(),
+        )
+        .save(output.path())
+        .unwrap();
+
+        assert!(CrawlSnapshot::load(output.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn other_version() {
+        let output = tempfile::tempdir().unwrap();
+        std::fs::write(
+            output.path().join(STATE_FILE_NAME),
+            r#"{"version":0,"queue":[]}"#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            CrawlSnapshot::load(output.path()),
+            Err(Error::StateVersion { version: 0 })
+        ));
+    }
+}
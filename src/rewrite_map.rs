@@ -0,0 +1,167 @@
+use std::{
+    collections::BTreeMap,
+    fs::{read_to_string, File},
+    io::{BufWriter, ErrorKind, Write},
+    path::Path,
+};
+
+use dashmap::DashMap;
+use reqwest::Url;
+
+use crate::{Error, Result};
+
+/// Name of the rewrite map in the output directory
+pub const REWRITE_MAP_FILE_NAME: &str = "REWRITE_MAP";
+
+/// Files of the mirror by the host and request uri they were downloaded from
+///
+/// The map is written as the body of an nginx `map` block, e.g.
+///
+/// ```nginx
+/// map $host$request_uri $mirror_file {
+///     include /path/to/mirror/REWRITE_MAP;
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct RewriteMap {
+    files: DashMap<String, String>,
+}
+
+impl RewriteMap {
+    /// Records that `url` was saved to `path` relative to the output directory
+    pub fn insert(&self, url: &Url, path: &Path) {
+        if let Some(key) = request_key(url) {
+            self.files
+                .insert(key, format!("/{}", path.to_string_lossy()));
+        }
+    }
+
+    /// Points the urls saved to `from` to `to`, where the file was moved
+    pub fn rename(&self, from: &Path, to: &Path) {
+        let from = format!("/{}", from.to_string_lossy());
+        self.files
+            .iter_mut()
+            .filter(|entry| *entry.value() == from)
+            .for_each(|mut entry| *entry.value_mut() = format!("/{}", to.to_string_lossy()));
+    }
+
+    /// Adds the files to the map in `output_path`, keeping the entries of
+    /// earlier crawls
+    pub fn write(&self, output_path: &Path) -> Result<()> {
+        let mut entries = read(output_path)?;
+        for entry in self.files.iter() {
+            entries.insert(entry.key().clone(), entry.value().clone());
+        }
+
+        let file =
+            File::create(output_path.join(REWRITE_MAP_FILE_NAME)).map_err(Error::CreateFile)?;
+        let mut writer = BufWriter::new(file);
+
+        entries
+            .iter()
+            .try_for_each(|(key, file)| writeln!(writer, "{} {};", quote(key), quote(file)))
+            .and_then(|_| writer.flush())
+            .map_err(Error::WriteFile)
+    }
+}
+
+/// Reads the map in `output_path`, which is empty if there is none
+pub fn read(output_path: &Path) -> Result<BTreeMap<String, String>> {
+    match read_to_string(output_path.join(REWRITE_MAP_FILE_NAME)) {
+        Ok(map) => Ok(map.lines().filter_map(parse_line).collect()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(err) => Err(Error::ReadFile(err)),
+    }
+}
+
+/// `$host$request_uri` of a request for `url`
+pub(crate) fn request_key(url: &Url) -> Option<String> {
+    let host = url.host_str()?;
+    let query = url.query().map(|query| format!("?{query}"));
+
+    Some(format!("{host}{}{}", url.path(), query.unwrap_or_default()))
+}
+
+/// Quotes `s` as an nginx string
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Reads an entry written by [`RewriteMap::write`]
+fn parse_line(line: &str) -> Option<(String, String)> {
+    let mut strings = Vec::with_capacity(2);
+    let mut chars = line.chars();
+
+    while strings.len() < 2 {
+        // skip to the opening quote
+        chars.by_ref().find(|&c| c == '"')?;
+
+        let mut string = String::new();
+        loop {
+            match chars.next()? {
+                '\\' => string.push(chars.next()?),
+                '"' => break,
+                c => string.push(c),
+            }
+        }
+        strings.push(string);
+    }
+
+    let file = strings.pop()?;
+    let key = strings.pop()?;
+    Some((key, file))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn query() {
+        let output = tempfile::tempdir().unwrap();
+        let map = RewriteMap::default();
+
+        map.insert(
+            &Url::parse("https://example.com/search?q=a\"b").unwrap(),
+            Path::new("example.com/search?q=a%22b"),
+        );
+        map.write(output.path()).unwrap();
+
+        assert_eq!(
+            "\"example.com/search?q=a%22b\" \"/example.com/search?q=a%22b\";\n",
+            read_to_string(output.path().join(REWRITE_MAP_FILE_NAME)).unwrap()
+        );
+    }
+
+    #[test]
+    fn keeps_earlier_entries() {
+        let output = tempfile::tempdir().unwrap();
+
+        let first = RewriteMap::default();
+        first.insert(
+            &Url::parse("https://example.com/b").unwrap(),
+            Path::new("example.com/b"),
+        );
+        first.write(output.path()).unwrap();
+
+        let second = RewriteMap::default();
+        second.insert(
+            &Url::parse("https://example.com/a").unwrap(),
+            Path::new("example.com/a"),
+        );
+        second.write(output.path()).unwrap();
+
+        assert_eq!(
+            "\"example.com/a\" \"/example.com/a\";\n\"example.com/b\" \"/example.com/b\";\n",
+            read_to_string(output.path().join(REWRITE_MAP_FILE_NAME)).unwrap()
+        );
+    }
+
+    #[test]
+    fn escaped_quotes() {
+        assert_eq!(
+            Some((String::from("a\"b"), String::from("/c\\d"))),
+            parse_line(&format!("{} {};", quote("a\"b"), quote("/c\\d")))
+        );
+    }
+}
@@ -0,0 +1,158 @@
+/// The rules of a `robots.txt` which apply to one user agent
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RobotsTxt {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+impl RobotsTxt {
+    /// Parses the groups of `robots_txt` for `user_agent`.
+    ///
+    /// Groups naming the user agent take precedence over the `*` group.
+    pub fn parse(robots_txt: &str, user_agent: &str) -> Self {
+        let mut specific = Vec::new();
+        let mut wildcard = Vec::new();
+        let mut found_specific = false;
+
+        // user agents of the current group
+        let mut agents = Vec::new();
+        let mut in_rules = false;
+
+        for line in robots_txt.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field.trim().to_ascii_lowercase(), value.trim()),
+                None => continue,
+            };
+
+            match field.as_str() {
+                "user-agent" => {
+                    // a user agent after rules starts a new group
+                    if in_rules {
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_ascii_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+
+                    // an empty disallow allows everything
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let rule = Rule {
+                        allow: field == "allow",
+                        pattern: value.to_string(),
+                    };
+
+                    if agents.iter().any(|agent| matches_agent(agent, user_agent)) {
+                        found_specific = true;
+                        specific.push(rule);
+                    } else if agents.iter().any(|agent| agent == "*") {
+                        wildcard.push(rule);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            rules: if found_specific { specific } else { wildcard },
+        }
+    }
+
+    /// Whether `path`, including the query, may be crawled.
+    ///
+    /// The longest matching rule wins, allow rules win ties.
+    pub fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| matches_pattern(&rule.pattern, path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .map_or(true, |rule| rule.allow)
+    }
+}
+
+fn matches_agent(agent: &str, user_agent: &str) -> bool {
+    agent != "*" && user_agent.to_ascii_lowercase().starts_with(agent)
+}
+
+/// Matches `path` against a pattern supporting `*` and a trailing `$`
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match path.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let parts = parts.collect::<Vec<_>>();
+    for (index, part) in parts.iter().enumerate() {
+        // the last part of an anchored pattern has to end the path
+        if anchored && index == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+
+    !anchored || rest.is_empty()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ROBOTS_TXT: &str = "
+        User-agent: *
+        Disallow: /private/
+        Allow: /private/public.html
+        Disallow: /*.pdf$
+
+        # only for us
+        User-agent: other
+        User-agent: wmt
+        Disallow: /no-wmt/
+    ";
+
+    #[test]
+    fn wildcard_group() {
+        let robots = RobotsTxt::parse(ROBOTS_TXT, "somebot/1.0");
+
+        assert!(robots.allows("/"));
+        assert!(!robots.allows("/private/secret.html"));
+        assert!(robots.allows("/private/public.html"));
+        assert!(!robots.allows("/docs/manual.pdf"));
+        assert!(robots.allows("/docs/manual.pdf?download=1"));
+    }
+
+    #[test]
+    fn specific_group() {
+        let robots = RobotsTxt::parse(ROBOTS_TXT, "wmt/0.1.0");
+
+        assert!(!robots.allows("/no-wmt/page"));
+        // the wildcard group does not apply anymore
+        assert!(robots.allows("/private/secret.html"));
+    }
+
+    #[test]
+    fn empty() {
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow:\n", "wmt");
+
+        assert!(robots.allows("/anything"));
+        assert!(RobotsTxt::default().allows("/"));
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    fs::{remove_file, File, OpenOptions},
+    io::{self, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{write::GzEncoder, Compression};
+
+/// How metadata files of the mirror are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rotation {
+    /// Size after which the next line goes to a new file
+    pub max_bytes: Option<u64>,
+    /// Compress every file with gzip
+    pub gzip: bool,
+}
+
+impl Rotation {
+    /// Creates `path` and removes the files rotated out of it by earlier crawls
+    pub fn create(self, path: &Path) -> io::Result<RotatingWriter> {
+        for index in 1.. {
+            let stale = [false, true]
+                .iter()
+                .filter(|&&gzip| remove_file(file_path(path, index, gzip)).is_ok())
+                .count();
+            if stale == 0 {
+                break;
+            }
+        }
+
+        let mut writer = RotatingWriter {
+            rotation: self,
+            path: path.to_path_buf(),
+            index: 0,
+            written: 0,
+            pending: false,
+            inner: None,
+        };
+        writer.open(false)?;
+
+        Ok(writer)
+    }
+
+    /// Continues the last file rotated out of `path`, or `path` itself if
+    /// there is none, keeping what earlier crawls wrote
+    pub fn append(self, path: &Path) -> io::Result<RotatingWriter> {
+        let index = (1..)
+            .take_while(|&index| file_path(path, index, self.gzip).exists())
+            .last()
+            .unwrap_or(0);
+
+        let mut writer = RotatingWriter {
+            rotation: self,
+            path: path.to_path_buf(),
+            index,
+            written: 0,
+            pending: false,
+            inner: None,
+        };
+        writer.open(true)?;
+
+        Ok(writer)
+    }
+}
+
+/// Writes to numbered files, starting a new one at the first line break after
+/// the configured size
+pub struct RotatingWriter {
+    rotation: Rotation,
+    path: PathBuf,
+    /// Number of the current file, the first has none
+    index: usize,
+    /// Uncompressed bytes written to the current file
+    written: u64,
+    /// The current file is full, the next write goes to a new one
+    pending: bool,
+    inner: Option<Inner>,
+}
+
+enum Inner {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl Inner {
+    fn get_mut(&mut self) -> &mut dyn Write {
+        match self {
+            Self::Plain(writer) => writer,
+            Self::Gzip(writer) => writer,
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Plain(mut writer) => writer.flush(),
+            Self::Gzip(writer) => writer.finish()?.flush(),
+        }
+    }
+}
+
+impl RotatingWriter {
+    /// Opens the current file, after its contents if `append` is set
+    fn open(&mut self, append: bool) -> io::Result<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(file_path(&self.path, self.index, self.rotation.gzip))?;
+        // the compressed size of an appended gzip file stands in for the
+        // bytes written to it, new members are appended to the stream
+        self.written = file.metadata()?.len();
+        self.pending = self.is_full() && self.written > 0;
+
+        let file = BufWriter::new(file);
+        self.inner = Some(if self.rotation.gzip {
+            Inner::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            Inner::Plain(file)
+        });
+
+        Ok(())
+    }
+
+    /// Whether the current file reached the configured size
+    fn is_full(&self) -> bool {
+        matches!(self.rotation.max_bytes, Some(max_bytes) if self.written >= max_bytes)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.finish_file()?;
+        self.index += 1;
+        self.open(false)
+    }
+
+    fn finish_file(&mut self) -> io::Result<()> {
+        match self.inner.take() {
+            Some(inner) => inner.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes all buffered data, completing the current file
+    pub fn finish(mut self) -> io::Result<()> {
+        self.finish_file()
+    }
+}
+
+impl Write for RotatingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.pending {
+            self.rotate()?;
+            self.pending = false;
+        }
+
+        let inner = match &mut self.inner {
+            Some(inner) => inner.get_mut(),
+            None => return Err(io::Error::new(ErrorKind::Other, "writer is finished")),
+        };
+
+        let written = inner.write(buf)?;
+        self.written += written as u64;
+
+        self.pending = self.is_full() && buf[..written].ends_with(b"\n");
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            Some(inner) => inner.get_mut().flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `path` with the number of the rotated file and the compression extension
+fn file_path(path: &Path, index: usize, gzip: bool) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    if index > 0 {
+        path.push(format!(".{index}"));
+    }
+    if gzip {
+        path.push(".gz");
+    }
+
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs::read_to_string, io::Read};
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    fn write_lines(rotation: Rotation, path: &Path, lines: usize) {
+        let mut writer = rotation.create(path).unwrap();
+        for line in 0..lines {
+            writeln!(writer, "line {line}").unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log");
+        let rotation = Rotation {
+            max_bytes: Some(12),
+            gzip: false,
+        };
+
+        write_lines(rotation, &path, 3);
+
+        assert_eq!("line 0\nline 1\n", read_to_string(&path).unwrap());
+        assert_eq!(
+            "line 2\n",
+            read_to_string(dir.path().join("log.1")).unwrap()
+        );
+        assert!(!dir.path().join("log.2").exists());
+    }
+
+    #[test]
+    fn removes_stale_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log");
+        let rotation = Rotation {
+            max_bytes: Some(1),
+            gzip: false,
+        };
+
+        write_lines(rotation, &path, 3);
+        write_lines(Rotation::default(), &path, 3);
+
+        assert!(!dir.path().join("log.1").exists());
+        assert!(!dir.path().join("log.2").exists());
+    }
+
+    #[test]
+    fn gzip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log");
+        let rotation = Rotation {
+            max_bytes: Some(7),
+            gzip: true,
+        };
+
+        write_lines(rotation, &path, 2);
+
+        let mut decoded = String::new();
+        GzDecoder::new(File::open(dir.path().join("log.1.gz")).unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!("line 1\n", decoded);
+        assert!(dir.path().join("log.gz").exists());
+        assert!(!dir.path().join("log.2.gz").exists());
+    }
+
+    #[test]
+    fn appends() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log");
+        let rotation = Rotation {
+            max_bytes: Some(12),
+            gzip: false,
+        };
+
+        for line in 0..3 {
+            let mut writer = rotation.append(&path).unwrap();
+            writeln!(writer, "line {line}").unwrap();
+            writer.finish().unwrap();
+        }
+
+        assert_eq!("line 0\nline 1\n", read_to_string(&path).unwrap());
+        assert_eq!(
+            "line 2\n",
+            read_to_string(dir.path().join("log.1")).unwrap()
+        );
+    }
+}
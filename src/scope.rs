@@ -0,0 +1,55 @@
+use std::{fmt, net::IpAddr};
+
+use reqwest::Url;
+
+/// Why a url is not crawled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutOfScope {
+    /// The url uses a non-standard port which is not allowed
+    Port(u16),
+    /// No target is on the host of the url
+    Domain,
+    /// The url is not below the path of a target on its host
+    Path,
+    /// The url matches no include or an exclude pattern
+    Pattern,
+    /// The extension of the url is not accepted or rejected
+    Extension,
+    /// The host of the url used up its byte budget
+    ByteBudget,
+    /// The robots.txt of the host disallows the url
+    RobotsTxt,
+    /// The host of the url resolves to a private address
+    PrivateAddress(IpAddr),
+}
+
+impl fmt::Display for OutOfScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Port(port) => write!(f, "port {port} is not allowed"),
+            Self::Domain => f.write_str("domain does not match any target"),
+            Self::Path => f.write_str("path is outside of the target paths"),
+            Self::Pattern => f.write_str("excluded by the url patterns"),
+            Self::Extension => f.write_str("excluded by the accepted extensions"),
+            Self::ByteBudget => f.write_str("host exceeded its byte budget"),
+            Self::RobotsTxt => f.write_str("disallowed by robots.txt"),
+            Self::PrivateAddress(ip) => write!(f, "resolves to the private address {ip}"),
+        }
+    }
+}
+
+/// Whether a discovered url would be crawled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeDecision {
+    pub url: Url,
+    pub out_of_scope: Option<OutOfScope>,
+}
+
+impl fmt::Display for ScopeDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.out_of_scope {
+            None => write!(f, "in   {}", self.url),
+            Some(reason) => write!(f, "out  {} ({reason})", self.url),
+        }
+    }
+}
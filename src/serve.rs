@@ -0,0 +1,338 @@
+use std::{
+    fs::{read, read_dir},
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    thread,
+};
+
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+
+use crate::{convert, escape_path::QUERY_SEPARATOR, looks_like_page, mime_ext, url_to_path};
+
+/// What a request path of the mirror refers to
+#[derive(Debug, PartialEq, Eq)]
+enum Lookup {
+    File(PathBuf),
+    /// A directory without an `index.html`
+    Listing(PathBuf),
+    /// A directory requested without the trailing slash
+    Redirect(String),
+    NotFound,
+}
+
+/// Serves the mirror in `root` to the connections of `listener`
+///
+/// Blocks until accepting fails, every connection is handled on its own thread.
+pub fn serve(root: &Path, listener: TcpListener) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let root = root.to_path_buf();
+
+        thread::spawn(move || {
+            // a closed connection only concerns this request
+            let _ = respond(stream, &root);
+        });
+    }
+
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream, root: &Path) -> io::Result<()> {
+    let mut reader = BufReader::new(&mut stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // the headers do not change the response
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line != "\r\n" {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let head = method == "HEAD";
+
+    if method != "GET" && !head {
+        return write_response(stream, "405 Method Not Allowed", &[], b"", false);
+    }
+
+    match resolve(root, target) {
+        Lookup::File(path) => match read(&path) {
+            Ok(body) => write_response(
+                stream,
+                "200 OK",
+                &[("Content-Type", content_type(&path))],
+                &body,
+                head,
+            ),
+            Err(_) => write_response(stream, "404 Not Found", &[], b"", head),
+        },
+        Lookup::Listing(path) => {
+            let body = listing(&path)?;
+            write_response(
+                stream,
+                "200 OK",
+                &[("Content-Type", "text/html; charset=utf-8")],
+                body.as_bytes(),
+                head,
+            )
+        }
+        Lookup::Redirect(location) => write_response(
+            stream,
+            "301 Moved Permanently",
+            &[("Location", &location)],
+            b"",
+            head,
+        ),
+        Lookup::NotFound => write_response(stream, "404 Not Found", &[], b"", head),
+    }
+}
+
+/// Finds the file `target` refers to, like `url_to_path` in reverse
+///
+/// Converted links name the saved files, other links name the paths of urls
+/// on the host in the first segment.
+fn resolve(root: &Path, target: &str) -> Lookup {
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (target, None),
+    };
+    let decoded = percent_decode_str(path).decode_utf8_lossy();
+    // requests never leave the mirror
+    if decoded.split('/').any(|segment| segment == "..") {
+        return Lookup::NotFound;
+    }
+    let relative = decoded.trim_start_matches('/');
+
+    if let Some(query) = query {
+        return match Url::parse(&format!("http://{relative}?{query}"))
+            .ok()
+            .and_then(|url| url_to_path(&url))
+        {
+            Some(path) => find_file(&root.join(path)),
+            None => Lookup::NotFound,
+        };
+    }
+
+    let path = root.join(relative);
+    if path.is_dir() {
+        // relative links of the index resolve against the directory
+        if !decoded.is_empty() && !decoded.ends_with('/') {
+            return Lookup::Redirect(format!("{target}/"));
+        }
+
+        let index = path.join("index.html");
+        return if index.is_file() {
+            Lookup::File(index)
+        } else {
+            Lookup::Listing(path)
+        };
+    }
+
+    find_file(&path)
+}
+
+/// The file at `path` or the one saved there with the extension of its type,
+/// like `avatar?id=42.jpg`
+fn find_file(path: &Path) -> Lookup {
+    if path.is_file() {
+        return Lookup::File(path.to_path_buf());
+    }
+
+    let (parent, name) = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => (parent, format!("{}.", name.to_string_lossy())),
+        _ => return Lookup::NotFound,
+    };
+
+    read_dir(parent)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.is_file()
+                && path.file_name().map_or(false, |file_name| {
+                    file_name.to_string_lossy().starts_with(&name)
+                })
+        })
+        .map_or(Lookup::NotFound, Lookup::File)
+}
+
+/// `Content-Type` of the saved file at `path`
+///
+/// Files without a known extension are served as pages if their name looks
+/// like one.
+fn content_type(path: &Path) -> &'static str {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let without_query = name.split(QUERY_SEPARATOR).next().unwrap_or_default();
+
+    [name.as_ref(), without_query]
+        .iter()
+        .find_map(|name| mime_ext::mime_type(name.rsplit_once('.')?.1))
+        .unwrap_or_else(|| {
+            if looks_like_page(without_query) {
+                "text/html"
+            } else {
+                "application/octet-stream"
+            }
+        })
+}
+
+/// A page linking the entries of the directory at `path`
+fn listing(path: &Path) -> io::Result<String> {
+    let mut names = read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // sidecar files of the crawl
+            if name.starts_with('.') {
+                return None;
+            }
+            Some(if entry.path().is_dir() {
+                format!("{name}/")
+            } else {
+                name
+            })
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+
+    let links = names
+        .iter()
+        .map(|name| {
+            let href = convert::escape(name).replace('&', "&amp;");
+            let name = name.replace('&', "&amp;").replace('<', "&lt;");
+            format!("<li><a href=\"{href}\">{name}</a></li>\n")
+        })
+        .collect::<String>();
+
+    Ok(format!(
+        "<!DOCTYPE html>\n<meta charset=\"utf-8\">\n<ul>\n{links}</ul>\n"
+    ))
+}
+
+fn write_response(
+    mut stream: TcpStream,
+    status: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    head: bool,
+) -> io::Result<()> {
+    write!(stream, "HTTP/1.1 {status}\r\n")?;
+    for (name, value) in headers {
+        write!(stream, "{name}: {value}\r\n")?;
+    }
+    write!(
+        stream,
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    if !head {
+        stream.write_all(body)?;
+    }
+    stream.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs::{create_dir_all, write},
+        io::Read,
+    };
+
+    use super::*;
+
+    fn mirror() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        let site = root.path().join("example.com");
+        create_dir_all(site.join("docs")).unwrap();
+        write(site.join("index.html"), "home").unwrap();
+        write(site.join(format!("index.html{QUERY_SEPARATOR}page=2")), "2").unwrap();
+        write(
+            site.join(format!("avatar{QUERY_SEPARATOR}id=42.png")),
+            "png",
+        )
+        .unwrap();
+        write(site.join("docs/guide"), "guide").unwrap();
+        root
+    }
+
+    #[test]
+    fn lookups() {
+        let root = mirror();
+        let site = root.path().join("example.com");
+        let lookup = |target: &str| resolve(root.path(), target);
+
+        assert_eq!(
+            Lookup::File(site.join("index.html")),
+            lookup("/example.com/")
+        );
+        assert_eq!(
+            Lookup::Redirect(String::from("/example.com/")),
+            lookup("/example.com")
+        );
+        assert_eq!(
+            Lookup::File(site.join(format!("index.html{QUERY_SEPARATOR}page=2"))),
+            lookup("/example.com/?page=2")
+        );
+        assert_eq!(
+            Lookup::File(site.join(format!("index.html{QUERY_SEPARATOR}page=2"))),
+            lookup(&format!(
+                "/example.com/index.html{}page=2",
+                if cfg!(windows) { "%EF%BC%9F" } else { "%3F" }
+            ))
+        );
+        assert_eq!(
+            Lookup::File(site.join(format!("avatar{QUERY_SEPARATOR}id=42.png"))),
+            lookup("/example.com/avatar?id=42")
+        );
+        assert_eq!(
+            Lookup::Listing(site.join("docs")),
+            lookup("/example.com/docs/")
+        );
+        assert_eq!(Lookup::NotFound, lookup("/example.com/missing"));
+        assert_eq!(Lookup::NotFound, lookup("/example.com/../../etc/passwd"));
+    }
+
+    #[test]
+    fn content_types() {
+        assert_eq!("text/html", content_type(Path::new("index.html")));
+        assert_eq!(
+            "text/html",
+            content_type(Path::new(&format!("index.html{QUERY_SEPARATOR}page=2")))
+        );
+        assert_eq!(
+            "image/png",
+            content_type(Path::new(&format!("avatar{QUERY_SEPARATOR}id=42.png")))
+        );
+        assert_eq!("text/html", content_type(Path::new("docs/guide")));
+        assert_eq!(
+            "application/octet-stream",
+            content_type(Path::new("archive.tar"))
+        );
+    }
+
+    #[test]
+    fn serves_files() {
+        let root = mirror();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let path = root.path().to_path_buf();
+        thread::spawn(move || serve(&path, listener));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET /example.com/ HTTP/1.1\r\nHost: localhost\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/html\r\n"));
+        assert!(response.ends_with("\r\n\r\nhome"));
+    }
+}
@@ -0,0 +1,28 @@
+use std::path::{Path, PathBuf};
+
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+
+/// Prefixes `path` with two directory levels taken from the hash of `url`,
+/// e.g. `ab/cd/example.com/index.html`
+pub fn shard_path(url: &Url, path: &Path) -> PathBuf {
+    let digest = format!("{:x}", Sha256::digest(url.as_str().as_bytes()));
+
+    Path::new(&digest[..2]).join(&digest[2..4]).join(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prefix() {
+        let url = Url::parse("https://example.com/").unwrap();
+
+        // sha256("https://example.com/") starts with 0f115
+        assert_eq!(
+            PathBuf::from("0f/11/example.com/index.html"),
+            shard_path(&url, Path::new("example.com/index.html"))
+        );
+    }
+}
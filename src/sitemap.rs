@@ -0,0 +1,110 @@
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref LOC: Regex = Regex::new(r"(?is)<loc>\s*(.*?)\s*</loc>").unwrap();
+}
+
+/// Magic bytes gzip files start with
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Urls of the `<loc>` elements of a sitemap or sitemap index
+pub fn locs(sitemap: &str) -> Vec<String> {
+    LOC.captures_iter(sitemap)
+        .map(|captures| {
+            unescape(
+                captures[1]
+                    .trim_start_matches("<![CDATA[")
+                    .trim_end_matches("]]>"),
+            )
+        })
+        .filter(|loc| !loc.is_empty())
+        .collect()
+}
+
+/// Whether `sitemap` lists other sitemaps instead of pages
+pub fn is_index(sitemap: &str) -> bool {
+    sitemap.contains("<sitemapindex")
+}
+
+/// Text of a sitemap, decompressing gzipped ones like `sitemap.xml.gz`
+pub fn decode(bytes: &[u8]) -> Option<String> {
+    let bytes = if bytes.starts_with(&GZIP_MAGIC) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(bytes).read_to_end(&mut decompressed).ok()?;
+        decompressed
+    } else {
+        bytes.to_vec()
+    };
+
+    String::from_utf8(bytes).ok()
+}
+
+/// Replaces the predefined XML entities
+pub fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    const SITEMAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://example.com/</loc></url>
+  <url>
+    <loc>
+      https://example.com/search?q=a&amp;page=2
+    </loc>
+    <lastmod>2022-03-01</lastmod>
+  </url>
+  <url><LOC><![CDATA[https://example.com/about]]></LOC></url>
+</urlset>"#;
+
+    #[test]
+    fn urlset() {
+        assert_eq!(
+            vec![
+                "https://example.com/",
+                "https://example.com/search?q=a&page=2",
+                "https://example.com/about",
+            ],
+            locs(SITEMAP)
+        );
+        assert!(!is_index(SITEMAP));
+    }
+
+    #[test]
+    fn index() {
+        let index = r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap><loc>https://example.com/sitemap-pages.xml.gz</loc></sitemap>
+</sitemapindex>"#;
+
+        assert!(is_index(index));
+        assert_eq!(
+            vec!["https://example.com/sitemap-pages.xml.gz"],
+            locs(index)
+        );
+    }
+
+    #[test]
+    fn gzipped() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(SITEMAP.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        assert_eq!(Some(SITEMAP), decode(&gzipped).as_deref());
+        assert_eq!(Some(SITEMAP), decode(SITEMAP.as_bytes()).as_deref());
+    }
+}
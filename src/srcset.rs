@@ -0,0 +1,94 @@
+/// Extracts the urls of the image candidates in a `srcset` attribute.
+///
+/// Follows the parsing rules of the HTML standard: a url runs up to the next
+/// whitespace, so urls containing commas like data urls are kept whole, and
+/// the optional descriptor after it runs up to the next comma outside of
+/// parentheses.
+pub fn urls(srcset: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    let mut rest = srcset;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+
+        let end = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let (url, after) = rest.split_at(end);
+        rest = after;
+
+        let url = match url.trim_end_matches(',') {
+            // a trailing comma ends the candidate without a descriptor
+            trimmed if trimmed.len() < url.len() => trimmed,
+            url => {
+                rest = skip_descriptor(rest);
+                url
+            }
+        };
+
+        if !url.is_empty() {
+            urls.push(url.to_string());
+        }
+    }
+
+    urls
+}
+
+/// Skips to after the comma ending the descriptor at the start of `src`
+fn skip_descriptor(src: &str) -> &str {
+    let mut in_parens = false;
+
+    for (index, c) in src.char_indices() {
+        match c {
+            '(' => in_parens = true,
+            ')' => in_parens = false,
+            ',' if !in_parens => return &src[index + 1..],
+            _ => {}
+        }
+    }
+
+    ""
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn descriptors() {
+        assert_eq!(
+            vec!["a.jpg", "b.jpg", "c.jpg"],
+            urls("a.jpg 1x, b.jpg 2x,c.jpg 480w")
+        );
+    }
+
+    #[test]
+    fn whitespace() {
+        assert_eq!(
+            vec!["a.jpg", "b.jpg"],
+            urls("  \n a.jpg \t 1x ,\n\n  b.jpg   2x  ")
+        );
+    }
+
+    #[test]
+    fn empty_descriptors() {
+        assert_eq!(vec!["a.jpg", "b.jpg"], urls("a.jpg, b.jpg"));
+        assert_eq!(vec!["a.jpg"], urls(",,a.jpg,,"));
+        assert!(urls("").is_empty());
+    }
+
+    #[test]
+    fn commas_in_urls() {
+        assert_eq!(
+            vec!["data:image/png;base64,iVBORw0KGgo=", "b.jpg"],
+            urls("data:image/png;base64,iVBORw0KGgo= 1x, b.jpg 2x")
+        );
+        assert_eq!(
+            vec!["image.php?size=1,2", "b.jpg"],
+            urls("image.php?size=1,2 1x, b.jpg (future, descriptor) 2x")
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    fmt::Debug,
+    future::Future,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    sync::Arc,
+};
+
+use dashmap::DashMap;
+use hyper::client::connect::dns::Name;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    Url,
+};
+use tokio::net::lookup_host;
+use url::Host;
+
+/// The addresses a host name resolves to, once looked up
+pub type Lookup = Pin<Box<dyn Future<Output = io::Result<Vec<IpAddr>>> + Send>>;
+
+/// Resolves host names to addresses
+pub trait Resolver: Debug + Send + Sync {
+    fn resolve(&self, host: &str) -> Lookup;
+}
+
+/// Resolves host names using the system resolver
+#[derive(Debug, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn resolve(&self, host: &str) -> Lookup {
+        let host = host.to_string();
+
+        Box::pin(async move {
+            Ok(lookup_host((host.as_str(), 0))
+                .await?
+                .map(|addr| addr.ip())
+                .collect())
+        })
+    }
+}
+
+/// Decides whether urls point to loopback, link-local or private addresses
+///
+/// As the DNS resolver of a client it refuses to connect to hosts resolving
+/// to such addresses, which also covers the hops of redirects.
+#[derive(Debug, Clone)]
+pub struct AddressFilter {
+    resolver: Arc<dyn Resolver>,
+    /// Hosts which may resolve to private addresses
+    allowed_hosts: Arc<Vec<String>>,
+    /// Cached decisions per host
+    blocked: Arc<DashMap<String, Option<IpAddr>>>,
+}
+
+impl AddressFilter {
+    pub fn new(resolver: Arc<dyn Resolver>, allowed_hosts: Vec<String>) -> Self {
+        Self {
+            resolver,
+            allowed_hosts: Arc::new(allowed_hosts),
+            blocked: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Returns the private address `url` is known to resolve to, if any
+    ///
+    /// Never resolves the host, domains are only known once looked up by
+    /// `check` or a connection.
+    pub fn blocked_address(&self, url: &Url) -> Option<IpAddr> {
+        let host = url.host_str()?;
+
+        if self.is_allowed(host) {
+            return None;
+        }
+
+        match url.host()? {
+            Host::Ipv4(ip) => Some(IpAddr::V4(ip)).filter(|ip| is_private(*ip)),
+            Host::Ipv6(ip) => Some(IpAddr::V6(ip)).filter(|ip| is_private(*ip)),
+            Host::Domain(domain) => self.blocked.get(domain).and_then(|blocked| *blocked),
+        }
+    }
+
+    /// Returns the private address `url` resolves to, if any, looking up its
+    /// host unless known already
+    pub async fn check(&self, url: &Url) -> Option<IpAddr> {
+        let domain = match url.host() {
+            Some(Host::Domain(domain)) if !self.is_allowed(domain) => domain,
+            _ => return self.blocked_address(url),
+        };
+        if let Some(blocked) = self.blocked.get(domain) {
+            return *blocked;
+        }
+
+        // unresolvable hosts fail when fetching anyway
+        let ips = self.resolver.resolve(domain).await.unwrap_or_default();
+        self.decide(domain, &ips)
+    }
+
+    fn is_allowed(&self, host: &str) -> bool {
+        self.allowed_hosts.iter().any(|allowed| allowed == host)
+    }
+
+    /// Caches and returns the private address of `ips`, the addresses of `host`
+    fn decide(&self, host: &str, ips: &[IpAddr]) -> Option<IpAddr> {
+        let blocked = ips.iter().copied().find(|ip| is_private(*ip));
+        self.blocked.insert(host.to_string(), blocked);
+        blocked
+    }
+}
+
+impl Resolve for AddressFilter {
+    fn resolve(&self, name: Name) -> Resolving {
+        let filter = self.clone();
+
+        Box::pin(async move {
+            let host = name.as_str();
+            let ips = filter.resolver.resolve(host).await?;
+
+            if !filter.is_allowed(host) {
+                if let Some(ip) = filter.decide(host, &ips) {
+                    let message = format!("{host} resolves to the private address {ip}");
+                    return Err(io::Error::new(io::ErrorKind::PermissionDenied, message).into());
+                }
+            }
+
+            // the port is taken from the request url
+            let addrs: Addrs = Box::new(ips.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+/// Whether `ip` is a loopback, link-local, private or unspecified address
+pub fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_v4(ip),
+        IpAddr::V6(ip) => match ipv4_mapped(ip) {
+            Some(ip) => is_private_v4(ip),
+            None => {
+                let first = ip.segments()[0];
+
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    // unique local fc00::/7
+                    || first & 0xfe00 == 0xfc00
+                    // link-local fe80::/10
+                    || first & 0xffc0 == 0xfe80
+            }
+        },
+    }
+}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified()
+}
+
+fn ipv4_mapped(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    match ip.segments() {
+        [0, 0, 0, 0, 0, 0xffff, high, low] => {
+            Some(Ipv4Addr::from((u32::from(high) << 16) | u32::from(low)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn private_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.178.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:192.168.0.1",
+        ] {
+            assert!(is_private(ip.parse().unwrap()), "{ip} should be private");
+        }
+    }
+
+    #[test]
+    fn public_addresses() {
+        for ip in ["93.184.216.34", "172.32.0.1", "2606:2800:220:1::1"] {
+            assert!(!is_private(ip.parse().unwrap()), "{ip} should be public");
+        }
+    }
+}
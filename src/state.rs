@@ -0,0 +1,118 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use dashmap::{mapref::entry::Entry, DashMap, DashSet};
+use parking_lot::Mutex;
+use reqwest::Url;
+use tokio::sync::Semaphore;
+
+use crate::{
+    error_log::ErrorLog, long_paths::HashedPaths, manifest::Manifest, metrics::Metrics,
+    rewrite_map::RewriteMap, robots_txt::RobotsTxt,
+};
+
+/// State shared between all workers of a crawl
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    /// `@import` depth of every stylesheet seen so far
+    pub(crate) stylesheets: Arc<DashMap<Url, usize>>,
+    /// Link hops from the targets of every queued url, targets are missing
+    pub(crate) depths: Arc<DashMap<Url, usize>>,
+    /// Urls taken from the queue which are neither checked nor queued again yet,
+    /// with the workers holding them
+    pub(crate) in_flight: Arc<DashMap<Url, usize>>,
+    /// Failed attempts of every url which failed so far
+    pub(crate) retries: Arc<DashMap<Url, usize>>,
+    /// Counters of the whole crawl
+    pub(crate) metrics: Arc<Metrics>,
+    /// Files saved with hashed paths because theirs were too long or sharded
+    pub(crate) hashed_paths: Arc<HashedPaths>,
+    /// Saved files by the url they were downloaded from
+    pub(crate) rewrite_map: Arc<RewriteMap>,
+    /// Files saved for the JSON manifest
+    pub(crate) manifest: Arc<Manifest>,
+    /// Urls answered with an error status for the error log
+    pub(crate) error_log: Arc<ErrorLog>,
+    /// Rules of the robots.txt of every origin seen so far
+    pub(crate) robots_txt: Arc<DashMap<String, Arc<RobotsTxt>>>,
+    /// Origins whose sitemaps were fetched already
+    pub(crate) sitemaps: Arc<DashSet<String>>,
+    /// Earliest time of the next request per host
+    pub(crate) next_request: Arc<DashMap<String, Instant>>,
+    /// Time until which the bytes received so far use up the rate limit
+    pub(crate) next_transfer: Arc<Mutex<Option<Instant>>>,
+    /// Bytes saved per host
+    pub(crate) host_bytes: Arc<DashMap<String, AtomicU64>>,
+    /// First file saved with every content digest if deduplicating
+    pub(crate) content_hashes: Arc<DashMap<String, PathBuf>>,
+    /// Download slots per host if the downloads per host are limited
+    pub(crate) host_permits: Arc<DashMap<String, Arc<Semaphore>>>,
+    /// Set once the crawl should stop
+    shutdown: Arc<AtomicBool>,
+}
+
+impl State {
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    /// Tells all workers to stop after their current download
+    pub fn shutdown(&self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.shutdown.load(Ordering::Relaxed)
+    }
+
+    /// Counts `url` as in flight until the returned guard is dropped
+    pub(crate) fn take_off(&self, url: &Url) -> InFlight {
+        *self.in_flight.entry(url.clone()).or_insert(0) += 1;
+
+        InFlight {
+            urls: self.in_flight.clone(),
+            url: url.clone(),
+        }
+    }
+
+    pub fn hashed_paths(&self) -> &Arc<HashedPaths> {
+        &self.hashed_paths
+    }
+
+    pub fn rewrite_map(&self) -> &Arc<RewriteMap> {
+        &self.rewrite_map
+    }
+
+    pub fn manifest(&self) -> &Arc<Manifest> {
+        &self.manifest
+    }
+
+    pub fn error_log(&self) -> &Arc<ErrorLog> {
+        &self.error_log
+    }
+}
+
+/// Keeps a url in the in-flight urls of a crawl until dropped, which has to
+/// happen after it was checked or queued again
+#[derive(Debug)]
+pub(crate) struct InFlight {
+    urls: Arc<DashMap<Url, usize>>,
+    url: Url,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Entry::Occupied(mut entry) = self.urls.entry(self.url.clone()) {
+            *entry.get_mut() -= 1;
+            if *entry.get() == 0 {
+                entry.remove();
+            }
+        }
+    }
+}
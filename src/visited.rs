@@ -0,0 +1,144 @@
+use std::{fmt::Debug, fs::create_dir_all, path::Path, sync::Arc};
+
+use dashmap::DashSet;
+use lmdb::{Database, DatabaseFlags, Environment, Transaction, WriteFlags};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+
+use crate::{Error, Result};
+
+/// Size the store may grow to, only reserves address space
+const MAP_SIZE: u64 = 1 << 36;
+
+/// Named sets per store
+const MAX_SETS: u32 = 8;
+
+/// A set of urls
+pub trait VisitedStore: Debug + Send + Sync {
+    fn contains(&self, url: &Url) -> bool;
+
+    /// Returns whether `url` was not present before
+    fn insert(&self, url: Url) -> bool;
+
+    fn remove(&self, url: &Url);
+
+    /// All urls of the set, empty for stores which cannot list them
+    fn urls(&self) -> Vec<Url> {
+        Vec::new()
+    }
+}
+
+impl VisitedStore for DashSet<Url> {
+    fn contains(&self, url: &Url) -> bool {
+        DashSet::contains(self, url)
+    }
+
+    fn insert(&self, url: Url) -> bool {
+        DashSet::insert(self, url)
+    }
+
+    fn remove(&self, url: &Url) {
+        DashSet::remove(self, url);
+    }
+
+    fn urls(&self) -> Vec<Url> {
+        self.iter().map(|url| url.clone()).collect()
+    }
+}
+
+/// Opens the store at `path`, creating it if missing
+///
+/// Must only be called once per `path` and process.
+pub fn open_environment(path: &Path) -> Result<Arc<Environment>> {
+    create_dir_all(path).map_err(Error::CreateFile)?;
+
+    Environment::new()
+        .set_max_dbs(MAX_SETS)
+        .set_map_size(MAP_SIZE.min(usize::MAX as u64) as usize)
+        .open(path)
+        .map(Arc::new)
+        .map_err(Error::VisitedStore)
+}
+
+/// A set of urls kept on disk
+///
+/// Urls are stored by their SHA-256 digest as LMDB limits the key size.
+#[derive(Debug)]
+pub struct DiskStore {
+    env: Arc<Environment>,
+    db: Database,
+}
+
+impl DiskStore {
+    /// Opens the set `name` of the store `env`
+    pub fn new(env: Arc<Environment>, name: &str) -> Result<Self> {
+        let db = env
+            .create_db(Some(name), DatabaseFlags::empty())
+            .map_err(Error::VisitedStore)?;
+
+        Ok(Self { env, db })
+    }
+
+    fn key(url: &Url) -> [u8; 32] {
+        Sha256::digest(url.as_str().as_bytes()).into()
+    }
+}
+
+impl VisitedStore for DiskStore {
+    /// Read errors count as absent, the url is then checked again
+    fn contains(&self, url: &Url) -> bool {
+        self.env
+            .begin_ro_txn()
+            .and_then(|txn| txn.get(self.db, &Self::key(url)).map(|_| ()))
+            .is_ok()
+    }
+
+    /// Write errors count as newly inserted, the url is then checked again
+    fn insert(&self, url: Url) -> bool {
+        let inserted = self.env.begin_rw_txn().and_then(|mut txn| {
+            match txn.put(self.db, &Self::key(&url), &[], WriteFlags::NO_OVERWRITE) {
+                Err(lmdb::Error::KeyExist) => Ok(false),
+                result => result.and_then(|_| txn.commit()).map(|_| true),
+            }
+        });
+
+        inserted.unwrap_or(true)
+    }
+
+    /// Write errors keep the url, it is then not checked again
+    fn remove(&self, url: &Url) {
+        let _ = self.env.begin_rw_txn().and_then(|mut txn| {
+            match txn.del(self.db, &Self::key(url), None) {
+                Err(lmdb::Error::NotFound) => Ok(()),
+                result => result.and_then(|_| txn.commit()),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = Url::parse("https://example.com/").unwrap();
+
+        {
+            let store = DiskStore::new(open_environment(dir.path()).unwrap(), "checked").unwrap();
+            assert!(!store.contains(&url));
+            assert!(store.insert(url.clone()));
+            assert!(!store.insert(url.clone()));
+        }
+
+        let env = open_environment(dir.path()).unwrap();
+        let store = DiskStore::new(env.clone(), "checked").unwrap();
+        assert!(store.contains(&url));
+        assert!(!store.contains(&Url::parse("https://example.com/other").unwrap()));
+
+        // sets of the same store are separate
+        let other = DiskStore::new(env, "downloaded").unwrap();
+        assert!(!other.contains(&url));
+    }
+}
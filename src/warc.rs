@@ -0,0 +1,269 @@
+use std::{
+    fs::{create_dir_all, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use parking_lot::Mutex;
+use reqwest::{
+    header::{HeaderMap, HeaderName, CONTENT_LENGTH, TRANSFER_ENCODING},
+    Method, StatusCode, Url, Version,
+};
+
+/// Name of the WARC file written to the output directory
+pub const WARC_FILE_NAME: &str = "crawl.warc";
+
+/// Headers of responses which do not describe the decoded body of the block,
+/// kept under other names like other crawlers do
+const TRANSFER_HEADERS: [(HeaderName, &str); 2] = [
+    (TRANSFER_ENCODING, "x-crawler-transfer-encoding"),
+    (CONTENT_LENGTH, "x-crawler-content-length"),
+];
+
+/// A WARC file the requests and responses of the crawl are appended to
+///
+/// Records are written whole under the lock, so workers may share the file.
+#[derive(Debug)]
+pub struct WarcWriter {
+    path: PathBuf,
+    file: Mutex<BufWriter<File>>,
+}
+
+impl WarcWriter {
+    /// Opens the WARC file at `path` for appending and writes a `warcinfo`
+    /// record describing the crawl
+    pub fn create(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let writer = Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(BufWriter::new(file)),
+        };
+
+        let info = format!(
+            "software: wmt/{}\r\nformat: WARC File Format 1.1\r\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        let filename = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut file = writer.file.lock();
+        write_record(
+            &mut *file,
+            &[
+                ("WARC-Type", "warcinfo"),
+                ("WARC-Record-ID", &record_id()),
+                ("WARC-Date", &warc_date()),
+                ("WARC-Filename", &filename),
+                ("Content-Type", "application/warc-fields"),
+            ],
+            info.as_bytes(),
+        )?;
+        file.flush()?;
+        drop(file);
+
+        Ok(writer)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a `request` and a `response` record for one exchange with `url`
+    pub fn write_exchange(&self, url: &Url, request: &[u8], response: &[u8]) -> io::Result<()> {
+        let date = warc_date();
+        let request_id = record_id();
+        let response_id = record_id();
+
+        let mut file = self.file.lock();
+        write_record(
+            &mut *file,
+            &[
+                ("WARC-Type", "response"),
+                ("WARC-Record-ID", &response_id),
+                ("WARC-Date", &date),
+                ("WARC-Target-URI", url.as_str()),
+                ("Content-Type", "application/http;msgtype=response"),
+            ],
+            response,
+        )?;
+        write_record(
+            &mut *file,
+            &[
+                ("WARC-Type", "request"),
+                ("WARC-Record-ID", &request_id),
+                ("WARC-Date", &date),
+                ("WARC-Target-URI", url.as_str()),
+                ("WARC-Concurrent-To", &response_id),
+                ("Content-Type", "application/http;msgtype=request"),
+            ],
+            request,
+        )?;
+        // complete exchanges survive an aborted crawl
+        file.flush()
+    }
+}
+
+/// Serializes a request to `url` the way it is sent over HTTP/1.1
+pub fn request_block(method: &Method, url: &Url, headers: &HeaderMap) -> Vec<u8> {
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+
+    let mut block = format!("{method} {target} HTTP/1.1\r\n").into_bytes();
+    if !headers.contains_key("host") {
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            _ => String::new(),
+        };
+        block.extend_from_slice(format!("Host: {host}\r\n").as_bytes());
+    }
+    write_headers(&mut block, headers);
+    block.extend_from_slice(b"\r\n");
+
+    block
+}
+
+/// Serializes a response with its status line, headers and `body`
+///
+/// The body is the decoded one, reqwest removes the `Content-Encoding` of the
+/// bodies it decodes and the transfer headers are renamed.
+pub fn response_block(
+    version: Version,
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Vec<u8> {
+    let mut block = format!(
+        "{version:?} {} {}\r\n",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or_default()
+    )
+    .into_bytes();
+    for (name, value) in headers {
+        let name = TRANSFER_HEADERS
+            .iter()
+            .find(|(transfer_header, _)| transfer_header == name)
+            .map_or(name.as_str(), |(_, renamed)| renamed);
+        write_header(&mut block, name, value.as_bytes());
+    }
+    write_header(
+        &mut block,
+        CONTENT_LENGTH.as_str(),
+        body.len().to_string().as_bytes(),
+    );
+    block.extend_from_slice(b"\r\n");
+    block.extend_from_slice(body);
+
+    block
+}
+
+fn write_headers(block: &mut Vec<u8>, headers: &HeaderMap) {
+    for (name, value) in headers {
+        write_header(block, name.as_str(), value.as_bytes());
+    }
+}
+
+fn write_header(block: &mut Vec<u8>, name: &str, value: &[u8]) {
+    block.extend_from_slice(name.as_bytes());
+    block.extend_from_slice(b": ");
+    block.extend_from_slice(value);
+    block.extend_from_slice(b"\r\n");
+}
+
+fn write_record<W: Write>(writer: &mut W, fields: &[(&str, &str)], block: &[u8]) -> io::Result<()> {
+    write!(writer, "WARC/1.1\r\n")?;
+    for (name, value) in fields {
+        write!(writer, "{name}: {value}\r\n")?;
+    }
+    write!(writer, "Content-Length: {}\r\n\r\n", block.len())?;
+    writer.write_all(block)?;
+    writer.write_all(b"\r\n\r\n")
+}
+
+/// A random version 4 UUID as a record id
+fn record_id() -> String {
+    let mut bytes = rand::random::<[u8; 16]>();
+    bytes[6] = bytes[6] & 0x0f | 0x40;
+    bytes[8] = bytes[8] & 0x3f | 0x80;
+
+    let hex = bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    format!(
+        "<urn:uuid:{}-{}-{}-{}-{}>",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn warc_date() -> String {
+    DateTime::<Utc>::from(SystemTime::now()).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::read_to_string;
+
+    use reqwest::header::{HeaderValue, CONTENT_TYPE, USER_AGENT};
+
+    use super::*;
+
+    #[test]
+    fn exchange() {
+        let output = tempfile::tempdir().unwrap();
+        let path = output.path().join(WARC_FILE_NAME);
+        let url = Url::parse("http://localhost:8080/page?id=1").unwrap();
+
+        let mut request_headers = HeaderMap::new();
+        request_headers.insert(USER_AGENT, HeaderValue::from_static("wmt"));
+        let mut response_headers = HeaderMap::new();
+        response_headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        response_headers.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+
+        let warc = WarcWriter::create(&path).unwrap();
+        warc.write_exchange(
+            &url,
+            &request_block(&Method::GET, &url, &request_headers),
+            &response_block(Version::HTTP_11, StatusCode::OK, &response_headers, b"hi"),
+        )
+        .unwrap();
+
+        let records = read_to_string(&path).unwrap();
+        let records = records
+            .split("WARC/1.1\r\n")
+            .skip(1)
+            .map(|record| record.split_once("\r\n\r\n").unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(3, records.len());
+        assert!(records[0].0.contains("WARC-Type: warcinfo"));
+
+        let (fields, block) = records[1];
+        assert!(fields.contains("WARC-Type: response"));
+        assert!(fields.contains("WARC-Target-URI: http://localhost:8080/page?id=1"));
+        assert!(fields.contains("Content-Length: 104"));
+        assert_eq!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\n\
+             x-crawler-transfer-encoding: chunked\r\ncontent-length: 2\r\n\r\nhi\r\n\r\n",
+            block
+        );
+
+        let (fields, block) = records[2];
+        assert!(fields.contains("WARC-Type: request"));
+        assert!(fields.contains("WARC-Concurrent-To: <urn:uuid:"));
+        assert_eq!(
+            "GET /page?id=1 HTTP/1.1\r\nHost: localhost:8080\r\nuser-agent: wmt\r\n\r\n\r\n\r\n",
+            block
+        );
+    }
+}